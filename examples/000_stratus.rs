@@ -16,17 +16,14 @@ fn main() -> Result<(), &'static str> {
 
     while let Ok((n, _addr)) = sock.recv_from(&mut buff[..]) {
 
-        if let Ok(report) = rust_gdl90::StratusGDL90::from_udp_packet(&buff[..n]) {
-            match report {
-                StratusGDL90::TrafficReport(traffic) => {
-                    println!(
-                        "{:.2?} ICAO: 0x{:06X}, lat {:.4} [deg], long {:.4} [deg], alt {} [ft], {}",
-                        SystemTime::now().duration_since(UNIX_EPOCH).unwrap(),
-                        traffic.participant_address, traffic.latitude_deg, traffic.longitude_deg,
-                        traffic.pres_altitude_ft, traffic.callsign,
-                    );
-                },
-                _ => (),
+        for report in StratusGDL90::from_udp_datagram(&buff[..n]) {
+            if let Ok(StratusGDL90::TrafficReport(traffic)) = report {
+                println!(
+                    "{:.2?} ICAO: 0x{:06X}, lat {:.4?} [deg], long {:.4?} [deg], alt {:?} [ft], {}",
+                    SystemTime::now().duration_since(UNIX_EPOCH).unwrap(),
+                    traffic.participant_address, traffic.latitude_deg, traffic.longitude_deg,
+                    traffic.pres_altitude_ft, traffic.callsign,
+                );
             }
         }
 
@@ -0,0 +1,131 @@
+extern crate serde;
+
+use serde::{Serialize, Deserialize};
+
+use crate::geodesy::Enu;
+use crate::traffic_report::{TrackType, TrafficReport};
+
+// How much of a threat a target is, in increasing order
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThreatLevel {
+	Other,
+	Proximate,
+	TrafficAdvisory,
+}
+
+impl ThreatLevel {
+
+	// True if this level should set the traffic alert bit of a traffic report
+	pub fn is_alert(self) -> bool {
+		self == ThreatLevel::TrafficAdvisory
+	}
+
+}
+
+// TCAS-like thresholds for issuing a traffic advisory.  The defaults are the TA values for TCAS II sensitivity level
+// 5 (5000 to 10000 ft) and the usual proximate traffic limits of 6 NM and 1200 ft
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+	pub tau_s: f32,
+	pub dmod_nm: f32,
+	pub zthr_ft: f32,
+	pub proximate_range_nm: f32,
+	pub proximate_altitude_ft: f32,
+}
+
+impl Default for Thresholds {
+	fn default() -> Self {
+		Thresholds{ tau_s: 40.0, dmod_nm: 0.75, zthr_ft: 850.0, proximate_range_nm: 6.0, proximate_altitude_ft: 1200.0 }
+	}
+}
+
+// The geometry of an encounter between ownship and a target, assuming both keep their current velocities
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Encounter {
+	pub range_nm: f32,
+	// Negative when the target is getting closer
+	pub range_rate_kts: f32,
+	// Zero if the target is already moving away
+	pub time_to_cpa_s: f32,
+	pub horz_miss_nm: f32,
+	// Target altitude minus ownship altitude, now and at the closest point of approach.  None if either altitude is
+	// unknown
+	pub relative_altitude_ft: Option<f32>,
+	pub vert_miss_ft: Option<f32>,
+	pub threat: ThreatLevel,
+}
+
+// Horizontal position in NM east and north of the reference latitude and longitude
+fn local_position_nm(report:&TrafficReport, ref_lat_deg:f32, ref_lon_deg:f32) -> Option<(f32, f32)> {
+	let enu = Enu::between(ref_lat_deg as f64, ref_lon_deg as f64, 0.0, report.latitude_deg? as f64, report.longitude_deg? as f64, 0.0);
+	let m_per_nm:f64 = crate::METERS_PER_NM as f64;
+	Some(((enu.east_m / m_per_nm) as f32, (enu.north_m / m_per_nm) as f32))
+}
+
+// Velocity in knots east and north.  An unknown speed is treated as zero, and so is any speed when the track isn't
+// valid, since there's no direction to go with it
+fn velocity_kts(report:&TrafficReport) -> (f32, f32) {
+	if report.track_type == TrackType::NotValid {
+		return (0.0, 0.0);
+	}
+	let speed_kts:f32 = report.horz_velocity_kts.unwrap_or(0.0);
+	let trk_rad:f32   = report.track_heading_deg * crate::RAD_PER_DEG;
+	(speed_kts * trk_rad.sin(), speed_kts * trk_rad.cos())
+}
+
+// None if the position of either aircraft is unknown.  Unknown vertical velocities are treated as zero, and a target
+// with an unknown altitude is treated as co-altitude with ownship when deciding whether to alert
+pub fn assess(ownship:&TrafficReport, target:&TrafficReport, thresholds:&Thresholds) -> Option<Encounter> {
+	let (ref_lat_deg, ref_lon_deg) = (ownship.latitude_deg?, ownship.longitude_deg?);
+	let (x, y) = local_position_nm(target, ref_lat_deg, ref_lon_deg)?;
+
+	let (own_vx, own_vy) = velocity_kts(ownship);
+	let (tgt_vx, tgt_vy) = velocity_kts(target);
+	let (vx, vy) = (tgt_vx - own_vx, tgt_vy - own_vy);
+
+	let range_nm:f32       = (x*x + y*y).sqrt();
+	let range_rate_kts:f32 = if range_nm > 0.0 { (x*vx + y*vy) / range_nm } else { 0.0 };
+
+	let v2:f32 = vx*vx + vy*vy;
+	let time_to_cpa_h:f32 = if v2 > 0.0 { (-(x*vx + y*vy) / v2).max(0.0) } else { 0.0 };
+	let horz_miss_nm:f32  = ((x + vx*time_to_cpa_h).powi(2) + (y + vy*time_to_cpa_h).powi(2)).sqrt();
+
+	let vz_fpm:f32 = target.vert_velocity_fpm.unwrap_or(0.0) - ownship.vert_velocity_fpm.unwrap_or(0.0);
+	let relative_altitude_ft:Option<f32> = match (ownship.pres_altitude_ft, target.pres_altitude_ft) {
+		(Some(own_ft), Some(tgt_ft)) => Some(tgt_ft - own_ft),
+		_                            => None,
+	};
+	let vert_miss_ft:Option<f32> = relative_altitude_ft.map(|dz| dz + vz_fpm*time_to_cpa_h*60.0);
+
+	// Modified tau, which uses DMOD so that slowly closing targets still cause an alert when they get close
+	let horz_threat:bool = range_nm <= thresholds.dmod_nm || (range_rate_kts < 0.0 &&
+		(range_nm*range_nm - thresholds.dmod_nm*thresholds.dmod_nm) / (range_nm * -range_rate_kts) * 3600.0 <= thresholds.tau_s);
+	let vert_threat:bool = match relative_altitude_ft {
+		Some(dz) => dz.abs() <= thresholds.zthr_ft || (dz*vz_fpm < 0.0 && dz.abs() / vz_fpm.abs() * 60.0 <= thresholds.tau_s),
+		None     => true,
+	};
+	let proximate:bool = range_nm <= thresholds.proximate_range_nm &&
+		relative_altitude_ft.map(|dz| dz.abs() <= thresholds.proximate_altitude_ft).unwrap_or(true);
+
+	let threat:ThreatLevel = if horz_threat && vert_threat {
+		ThreatLevel::TrafficAdvisory
+	} else if proximate {
+		ThreatLevel::Proximate
+	} else {
+		ThreatLevel::Other
+	};
+
+	Some(Encounter {
+		range_nm, range_rate_kts,
+		time_to_cpa_s: time_to_cpa_h * 3600.0,
+		horz_miss_nm, relative_altitude_ft, vert_miss_ft, threat,
+	})
+}
+
+// Assesses the target and sets its traffic alert bit to match, so that it can be re-encoded for a display.  The bit
+// is cleared if the encounter can't be assessed
+pub fn update_traffic_alert(ownship:&TrafficReport, target:&mut TrafficReport, thresholds:&Thresholds) -> Option<Encounter> {
+	let encounter = assess(ownship, target, thresholds);
+	target.traffic_alert = encounter.map(|e| e.threat.is_alert()).unwrap_or(false);
+	encounter
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+
+// Everything that can go wrong while turning bytes into GDL90 messages.  Each variant keeps enough context to
+// explain in a log message why a message was rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gdl90Error {
+	// The packet didn't start with the 0x7E flag byte; holds the byte that was found instead
+	BadFlag(u8),
+	// The packet ended before the closing flag byte
+	Unterminated,
+	// The FCS calculated over the message (expected) doesn't match the one transmitted with it (actual)
+	CrcMismatch{ expected:u16, actual:u16 },
+	// A frame with no message ID
+	EmptyMessage,
+	// The message is shorter than its type requires
+	Truncated{ msg_id:u8, needed:usize, got:usize },
+	// A field has a value that isn't allowed by the spec
+	InvalidField{ field:&'static str, value:u32 },
+	// The message ID has sub-IDs and this one isn't known
+	UnknownSubId{ msg_id:u8, sub_id:u8 },
+	// Text that doesn't match the format expected for it, e.g. a METAR that doesn't match the METAR regex
+	Unrecognized(&'static str),
+}
+
+impl fmt::Display for Gdl90Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::BadFlag(b)    => write!(f, "expected flag byte 0x7E, found 0x{:02X}", b),
+			Self::Unterminated  => write!(f, "packet ended without a closing flag byte"),
+			Self::CrcMismatch{ expected, actual } => write!(f, "frame check sequence mismatch: calculated 0x{:04X}, received 0x{:04X}", expected, actual),
+			Self::EmptyMessage  => write!(f, "frame contains no message ID"),
+			Self::Truncated{ msg_id, needed, got } => write!(f, "message {} needs {} bytes but only {} were received", msg_id, needed, got),
+			Self::InvalidField{ field, value } => write!(f, "invalid value {} (0x{:X}) for {}", value, value, field),
+			Self::UnknownSubId{ msg_id, sub_id } => write!(f, "unknown sub-ID {} for message {}", sub_id, msg_id),
+			Self::Unrecognized(what) => write!(f, "unrecognized text: {}", what),
+		}
+	}
+}
+
+impl std::error::Error for Gdl90Error {}
+
+// Reading from a cursor can only fail by running out of bytes, so a failed read means the message was truncated.
+// The needed length doesn't include the message ID
+pub(crate) fn truncated(msg_id:u8, needed:usize, data:&[u8]) -> impl Fn(std::io::Error) -> Gdl90Error {
+	let got = data.len();
+	move |_| Gdl90Error::Truncated{ msg_id, needed, got }
+}
+
+pub(crate) fn require(msg_id:u8, needed:usize, data:&[u8]) -> Result<(), Gdl90Error> {
+	if data.len() < needed {
+		Err(Gdl90Error::Truncated{ msg_id, needed, got: data.len() })
+	} else {
+		Ok(())
+	}
+}
@@ -0,0 +1,69 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::{Gdl90Error, StratusGDL90};
+
+// Lets an extension value be turned into &dyn Any so that the caller can get the concrete type back.  This is
+// implemented for every type, so implementors of Extension don't need to do anything for it
+pub trait AsAny {
+	fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+	fn as_any(&self) -> &dyn Any { self }
+}
+
+// A message decoded by a decoder registered with a Registry, usually a vendor-specific message that this crate
+// doesn't know about
+pub trait Extension: AsAny + Debug + Send + Sync + 'static {
+	// The message data after the message ID, including the sub-ID if there is one; used by the encoder
+	fn to_bytes(&self) -> Vec<u8>;
+}
+
+impl dyn Extension {
+
+	pub fn downcast_ref<T: Extension>(&self) -> Option<&T> {
+		self.as_any().downcast_ref::<T>()
+	}
+
+}
+
+// Decoders receive the message data after the message ID, so the sub-ID (if any) is the first byte
+pub type Decoder = Box<dyn Fn(&[u8]) -> Result<Box<dyn Extension>, Gdl90Error> + Send + Sync>;
+
+// Decoders for message IDs that aren't built into this crate, or that should be decoded differently than the
+// built-in decoder does.  A decoder registered for a message ID and sub-ID takes priority over one registered for
+// the message ID alone, and both take priority over the built-in decoders
+#[derive(Default)]
+pub struct Registry {
+	decoders: HashMap<(u8, Option<u8>), Decoder>,
+}
+
+impl Registry {
+
+	pub fn new() -> Registry {
+		Registry{ decoders: HashMap::new() }
+	}
+
+	pub fn register<T, F>(&mut self, msg_id:u8, sub_id:Option<u8>, decoder:F)
+		where T: Extension, F: Fn(&[u8]) -> Result<T, Gdl90Error> + Send + Sync + 'static {
+		self.decoders.insert((msg_id, sub_id), Box::new(move |data:&[u8]| {
+			decoder(data).map(|value| Box::new(value) as Box<dyn Extension>)
+		}));
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.decoders.is_empty()
+	}
+
+	// Returns None if no decoder is registered for this message, in which case the built-in decoders should be used
+	pub fn decode(&self, msg_id:u8, data:&[u8]) -> Option<Result<StratusGDL90, Gdl90Error>> {
+		let decoder = data.first()
+			.and_then(|sub_id| self.decoders.get(&(msg_id, Some(*sub_id))))
+			.or_else(|| self.decoders.get(&(msg_id, None)))?;
+
+		Some(decoder(data).map(|value| StratusGDL90::Extension{ id: msg_id, value }))
+	}
+
+}
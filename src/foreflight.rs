@@ -0,0 +1,103 @@
+extern crate serde;
+
+use std::io::Cursor;
+
+use byteorder::{BigEndian, ReadBytesExt};
+use serde::{Serialize, Deserialize};
+
+use crate::Gdl90Error;
+use crate::error::{require, truncated};
+
+// Length of an ID message, not counting the message ID but including the sub-ID
+pub const DEVICE_ID_LENGTH:usize = 38;
+
+// ID message (ID 0x65, sub-ID 0) from the ForeFlight GDL90 extended specification
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeviceId {
+	pub version: u8,
+	// None when the device reports 0xFFFFFFFFFFFFFFFF, meaning the serial number is invalid
+	pub serial_number: Option<u64>,
+	pub name: String,
+	pub long_name: String,
+	pub geometric_altitude_datum: AltitudeDatum,
+	pub internet_policy: InternetPolicy,
+}
+
+// Reference for the altitude in the Ownship Geometric Altitude message
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AltitudeDatum {
+	Wgs84Ellipsoid,
+	MeanSeaLevel,
+}
+
+// Whether the app may use the internet while connected to the device
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum InternetPolicy {
+	Unrestricted,
+	Expensive,
+	Disallowed,
+	Reserved,
+}
+
+// Strings are UTF-8 and padded with nulls
+fn read_string(bytes:&[u8]) -> String {
+	String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string()
+}
+
+fn write_string(s:&str, len:usize) -> Vec<u8> {
+	let mut ans:Vec<u8> = s.bytes().take(len).collect();
+	ans.resize(len, 0x00);
+	ans
+}
+
+impl DeviceId {
+
+	// Decodes the message starting with the sub-ID
+	pub fn from_slice(data:&[u8]) -> Result<DeviceId, Gdl90Error> {
+		require(0x65, DEVICE_ID_LENGTH, data)?;
+		let t = truncated(0x65, DEVICE_ID_LENGTH, data);
+
+		// Skip the sub-ID
+		let mut rdr = Cursor::new(&data[1..]);
+		let version:u8       = rdr.read_u8().map_err(&t)?;
+		let serial_raw:u64   = rdr.read_u64::<BigEndian>().map_err(&t)?;
+		let name:String      = read_string(&data[10..18]);
+		let long_name:String = read_string(&data[18..34]);
+
+		let mut rdr = Cursor::new(&data[34..]);
+		let capabilities:u32 = rdr.read_u32::<BigEndian>().map_err(&t)?;
+
+		let serial_number = if serial_raw == u64::MAX { None } else { Some(serial_raw) };
+		let geometric_altitude_datum = if capabilities & 0x01 == 0 { AltitudeDatum::Wgs84Ellipsoid } else { AltitudeDatum::MeanSeaLevel };
+		let internet_policy = match (capabilities >> 1) & 0x03 {
+			0 => InternetPolicy::Unrestricted,
+			1 => InternetPolicy::Expensive,
+			2 => InternetPolicy::Disallowed,
+			_ => InternetPolicy::Reserved,
+		};
+
+		Ok(DeviceId{ version, serial_number, name, long_name, geometric_altitude_datum, internet_policy })
+	}
+
+	// The inverse of from_slice, starting with the sub-ID
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let datum_bit:u32 = match self.geometric_altitude_datum {
+			AltitudeDatum::Wgs84Ellipsoid => 0,
+			AltitudeDatum::MeanSeaLevel   => 1,
+		};
+		let policy_bits:u32 = match self.internet_policy {
+			InternetPolicy::Unrestricted => 0,
+			InternetPolicy::Expensive    => 1,
+			InternetPolicy::Disallowed   => 2,
+			InternetPolicy::Reserved     => 3,
+		};
+
+		let mut ans:Vec<u8> = vec![0x00, self.version];
+		ans.extend_from_slice(&self.serial_number.unwrap_or(u64::MAX).to_be_bytes());
+		ans.extend(write_string(&self.name, 8));
+		ans.extend(write_string(&self.long_name, 16));
+		ans.extend_from_slice(&((policy_bits << 1) | datum_bit).to_be_bytes());
+		ans
+	}
+
+}
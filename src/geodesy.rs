@@ -0,0 +1,277 @@
+extern crate serde;
+
+use serde::{Serialize, Deserialize};
+
+// Double-precision geodesy.  GDL90 positions have a resolution of about 2 m, which f32 can't hold on to once they've
+// been converted to radians and pushed through trigonometry, so everything here is f64
+
+// Mean radius used for the spherical model
+pub const MEAN_RADIUS_M:f64 = 6.371e6;
+
+// WGS-84 semi-major axis, flattening, semi-minor axis, and first eccentricity squared
+pub const WGS84_A:f64  = 6378137.0;
+pub const WGS84_F:f64  = 1.0 / 298.257223563;
+pub const WGS84_B:f64  = WGS84_A * (1.0 - WGS84_F);
+pub const WGS84_E2:f64 = WGS84_F * (2.0 - WGS84_F);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum EarthModel {
+	// Fast, but off by up to about 0.5% in distance
+	Sphere,
+	#[default]
+	Wgs84,
+}
+
+// Solution of the inverse problem: the distance and bearings along the shortest path between two points
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Geodesic {
+	pub distance_m: f64,
+	// Degrees true at the start and end of the path, from 0 to 360
+	pub initial_bearing_deg: f64,
+	pub final_bearing_deg: f64,
+}
+
+// Earth-centred, earth-fixed coordinates on WGS-84
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Ecef {
+	pub x_m: f64,
+	pub y_m: f64,
+	pub z_m: f64,
+}
+
+// East, north, and up from a reference point
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Enu {
+	pub east_m: f64,
+	pub north_m: f64,
+	pub up_m: f64,
+}
+
+fn normalize_bearing_deg(x:f64) -> f64 {
+	x.rem_euclid(360.0)
+}
+
+fn normalize_lon_deg(x:f64) -> f64 {
+	(x + 540.0).rem_euclid(360.0) - 180.0
+}
+
+// Great-circle distance on the spherical model
+pub fn haversine_m(lat1_deg:f64, lon1_deg:f64, lat2_deg:f64, lon2_deg:f64) -> f64 {
+	let phi1:f64 = lat1_deg.to_radians();
+	let phi2:f64 = lat2_deg.to_radians();
+	let dphi:f64 = phi2 - phi1;
+	let dlam:f64 = (lon2_deg - lon1_deg).to_radians();
+	let a:f64 = (0.5 * dphi).sin().powi(2) + phi1.cos()*phi2.cos()*(0.5*dlam).sin().powi(2);
+
+	MEAN_RADIUS_M * 2.0 * a.sqrt().atan2((1.0-a).sqrt())
+}
+
+fn sphere_bearing_deg(lat1_deg:f64, lon1_deg:f64, lat2_deg:f64, lon2_deg:f64) -> f64 {
+	let phi1:f64 = lat1_deg.to_radians();
+	let phi2:f64 = lat2_deg.to_radians();
+	let dlam:f64 = (lon2_deg - lon1_deg).to_radians();
+	let y:f64 = dlam.sin() * phi2.cos();
+	let x:f64 = phi1.cos()*phi2.sin() - phi1.sin()*phi2.cos()*dlam.cos();
+
+	normalize_bearing_deg(y.atan2(x).to_degrees())
+}
+
+fn sphere_inverse(lat1_deg:f64, lon1_deg:f64, lat2_deg:f64, lon2_deg:f64) -> Geodesic {
+	Geodesic {
+		distance_m:          haversine_m(lat1_deg, lon1_deg, lat2_deg, lon2_deg),
+		initial_bearing_deg: sphere_bearing_deg(lat1_deg, lon1_deg, lat2_deg, lon2_deg),
+		// The final bearing is the reverse of the initial bearing of the path from the second point to the first
+		final_bearing_deg:   normalize_bearing_deg(sphere_bearing_deg(lat2_deg, lon2_deg, lat1_deg, lon1_deg) + 180.0),
+	}
+}
+
+fn sphere_direct(lat1_deg:f64, lon1_deg:f64, bearing_deg:f64, distance_m:f64) -> (f64, f64) {
+	let delta:f64 = distance_m / MEAN_RADIUS_M;
+	let brng:f64  = bearing_deg.to_radians();
+	let phi1:f64  = lat1_deg.to_radians();
+	let lam1:f64  = lon1_deg.to_radians();
+
+	let phi2:f64 = (phi1.sin()*delta.cos() + phi1.cos()*delta.sin()*brng.cos()).asin();
+	let lam2:f64 = lam1 + (brng.sin()*delta.sin()*phi1.cos()).atan2(delta.cos() - phi1.sin()*phi2.sin());
+
+	(phi2.to_degrees(), normalize_lon_deg(lam2.to_degrees()))
+}
+
+// Vincenty's inverse formula on WGS-84, accurate to well under a millimetre.  None if it doesn't converge, which only
+// happens for nearly antipodal points
+pub fn vincenty_inverse(lat1_deg:f64, lon1_deg:f64, lat2_deg:f64, lon2_deg:f64) -> Option<Geodesic> {
+	let f:f64 = WGS84_F;
+	let l:f64 = normalize_lon_deg(lon2_deg - lon1_deg).to_radians();
+	let u1:f64 = ((1.0 - f) * lat1_deg.to_radians().tan()).atan();
+	let u2:f64 = ((1.0 - f) * lat2_deg.to_radians().tan()).atan();
+	let (sin_u1, cos_u1) = u1.sin_cos();
+	let (sin_u2, cos_u2) = u2.sin_cos();
+
+	let mut lambda:f64 = l;
+	for _ in 0..200 {
+		let (sin_lambda, cos_lambda) = lambda.sin_cos();
+		let sin_sigma:f64 = ((cos_u2*sin_lambda).powi(2) + (cos_u1*sin_u2 - sin_u1*cos_u2*cos_lambda).powi(2)).sqrt();
+		if sin_sigma == 0.0 {
+			// Coincident points
+			return Some(Geodesic{ distance_m: 0.0, initial_bearing_deg: 0.0, final_bearing_deg: 0.0 });
+		}
+		let cos_sigma:f64 = sin_u1*sin_u2 + cos_u1*cos_u2*cos_lambda;
+		let sigma:f64     = sin_sigma.atan2(cos_sigma);
+		let sin_alpha:f64 = cos_u1*cos_u2*sin_lambda / sin_sigma;
+		let cos2_alpha:f64 = 1.0 - sin_alpha*sin_alpha;
+		// cos2_alpha is zero for a path along the equator
+		let cos_2sigma_m:f64 = if cos2_alpha != 0.0 { cos_sigma - 2.0*sin_u1*sin_u2/cos2_alpha } else { 0.0 };
+		let c:f64 = f/16.0 * cos2_alpha * (4.0 + f*(4.0 - 3.0*cos2_alpha));
+
+		let lambda_prev:f64 = lambda;
+		lambda = l + (1.0 - c)*f*sin_alpha*(sigma + c*sin_sigma*(cos_2sigma_m + c*cos_sigma*(-1.0 + 2.0*cos_2sigma_m.powi(2))));
+		if (lambda - lambda_prev).abs() > 1e-12 {
+			continue;
+		}
+
+		let u_sq:f64 = cos2_alpha * (WGS84_A*WGS84_A - WGS84_B*WGS84_B) / (WGS84_B*WGS84_B);
+		let a:f64 = 1.0 + u_sq/16384.0*(4096.0 + u_sq*(-768.0 + u_sq*(320.0 - 175.0*u_sq)));
+		let b:f64 = u_sq/1024.0*(256.0 + u_sq*(-128.0 + u_sq*(74.0 - 47.0*u_sq)));
+		let delta_sigma:f64 = b*sin_sigma*(cos_2sigma_m + b/4.0*(cos_sigma*(-1.0 + 2.0*cos_2sigma_m.powi(2)) -
+			b/6.0*cos_2sigma_m*(-3.0 + 4.0*sin_sigma.powi(2))*(-3.0 + 4.0*cos_2sigma_m.powi(2))));
+
+		let (sin_lambda, cos_lambda) = lambda.sin_cos();
+		let alpha1:f64 = (cos_u2*sin_lambda).atan2(cos_u1*sin_u2 - sin_u1*cos_u2*cos_lambda);
+		let alpha2:f64 = (cos_u1*sin_lambda).atan2(-sin_u1*cos_u2 + cos_u1*sin_u2*cos_lambda);
+
+		return Some(Geodesic {
+			distance_m:          WGS84_B * a * (sigma - delta_sigma),
+			initial_bearing_deg: normalize_bearing_deg(alpha1.to_degrees()),
+			final_bearing_deg:   normalize_bearing_deg(alpha2.to_degrees()),
+		});
+	}
+
+	None
+}
+
+// Vincenty's direct formula on WGS-84, which always converges.  Returns the latitude and longitude of the end point
+pub fn vincenty_direct(lat1_deg:f64, lon1_deg:f64, bearing_deg:f64, distance_m:f64) -> (f64, f64) {
+	let f:f64 = WGS84_F;
+	let (sin_alpha1, cos_alpha1) = bearing_deg.to_radians().sin_cos();
+	let tan_u1:f64 = (1.0 - f) * lat1_deg.to_radians().tan();
+	let cos_u1:f64 = 1.0 / (1.0 + tan_u1*tan_u1).sqrt();
+	let sin_u1:f64 = tan_u1 * cos_u1;
+
+	let sigma1:f64     = tan_u1.atan2(cos_alpha1);
+	let sin_alpha:f64  = cos_u1 * sin_alpha1;
+	let cos2_alpha:f64 = 1.0 - sin_alpha*sin_alpha;
+	let u_sq:f64 = cos2_alpha * (WGS84_A*WGS84_A - WGS84_B*WGS84_B) / (WGS84_B*WGS84_B);
+	let a:f64 = 1.0 + u_sq/16384.0*(4096.0 + u_sq*(-768.0 + u_sq*(320.0 - 175.0*u_sq)));
+	let b:f64 = u_sq/1024.0*(256.0 + u_sq*(-128.0 + u_sq*(74.0 - 47.0*u_sq)));
+
+	let mut sigma:f64 = distance_m / (WGS84_B * a);
+	let mut cos_2sigma_m:f64 = (2.0*sigma1 + sigma).cos();
+	for _ in 0..200 {
+		cos_2sigma_m = (2.0*sigma1 + sigma).cos();
+		let (sin_sigma, cos_sigma) = sigma.sin_cos();
+		let delta_sigma:f64 = b*sin_sigma*(cos_2sigma_m + b/4.0*(cos_sigma*(-1.0 + 2.0*cos_2sigma_m.powi(2)) -
+			b/6.0*cos_2sigma_m*(-3.0 + 4.0*sin_sigma.powi(2))*(-3.0 + 4.0*cos_2sigma_m.powi(2))));
+		let sigma_prev:f64 = sigma;
+		sigma = distance_m / (WGS84_B * a) + delta_sigma;
+		if (sigma - sigma_prev).abs() <= 1e-12 {
+			break;
+		}
+	}
+
+	let (sin_sigma, cos_sigma) = sigma.sin_cos();
+	let x:f64 = sin_u1*sin_sigma - cos_u1*cos_sigma*cos_alpha1;
+	let phi2:f64 = (sin_u1*cos_sigma + cos_u1*sin_sigma*cos_alpha1).atan2((1.0 - f)*(sin_alpha*sin_alpha + x*x).sqrt());
+	let lambda:f64 = (sin_sigma*sin_alpha1).atan2(cos_u1*cos_sigma - sin_u1*sin_sigma*cos_alpha1);
+	let c:f64 = f/16.0 * cos2_alpha * (4.0 + f*(4.0 - 3.0*cos2_alpha));
+	let l:f64 = lambda - (1.0 - c)*f*sin_alpha*(sigma + c*sin_sigma*(cos_2sigma_m + c*cos_sigma*(-1.0 + 2.0*cos_2sigma_m.powi(2))));
+
+	(phi2.to_degrees(), normalize_lon_deg(lon1_deg + l.to_degrees()))
+}
+
+// Distance and bearings between two points.  On WGS-84, nearly antipodal points where Vincenty's formula doesn't
+// converge fall back to the sphere
+pub fn inverse(model:EarthModel, lat1_deg:f64, lon1_deg:f64, lat2_deg:f64, lon2_deg:f64) -> Geodesic {
+	match model {
+		EarthModel::Sphere => sphere_inverse(lat1_deg, lon1_deg, lat2_deg, lon2_deg),
+		EarthModel::Wgs84  => vincenty_inverse(lat1_deg, lon1_deg, lat2_deg, lon2_deg)
+			.unwrap_or_else(|| sphere_inverse(lat1_deg, lon1_deg, lat2_deg, lon2_deg)),
+	}
+}
+
+// The point at a distance and initial bearing from a starting point
+pub fn direct(model:EarthModel, lat1_deg:f64, lon1_deg:f64, bearing_deg:f64, distance_m:f64) -> (f64, f64) {
+	match model {
+		EarthModel::Sphere => sphere_direct(lat1_deg, lon1_deg, bearing_deg, distance_m),
+		EarthModel::Wgs84  => vincenty_direct(lat1_deg, lon1_deg, bearing_deg, distance_m),
+	}
+}
+
+impl Ecef {
+
+	pub fn from_geodetic(lat_deg:f64, lon_deg:f64, height_m:f64) -> Ecef {
+		let (sin_phi, cos_phi) = lat_deg.to_radians().sin_cos();
+		let (sin_lam, cos_lam) = lon_deg.to_radians().sin_cos();
+		let n:f64 = WGS84_A / (1.0 - WGS84_E2*sin_phi*sin_phi).sqrt();
+
+		Ecef {
+			x_m: (n + height_m) * cos_phi * cos_lam,
+			y_m: (n + height_m) * cos_phi * sin_lam,
+			z_m: (n*(1.0 - WGS84_E2) + height_m) * sin_phi,
+		}
+	}
+
+	// Latitude, longitude, and height above the ellipsoid, using Bowring's method, which is accurate to well under a
+	// millimetre anywhere an aircraft can be
+	pub fn to_geodetic(&self) -> (f64, f64, f64) {
+		let ep2:f64 = (WGS84_A*WGS84_A - WGS84_B*WGS84_B) / (WGS84_B*WGS84_B);
+		let p:f64 = (self.x_m*self.x_m + self.y_m*self.y_m).sqrt();
+		let theta:f64 = (self.z_m * WGS84_A).atan2(p * WGS84_B);
+		let (sin_theta, cos_theta) = theta.sin_cos();
+
+		let phi:f64 = (self.z_m + ep2*WGS84_B*sin_theta.powi(3)).atan2(p - WGS84_E2*WGS84_A*cos_theta.powi(3));
+		let lam:f64 = self.y_m.atan2(self.x_m);
+		let (sin_phi, cos_phi) = phi.sin_cos();
+		let n:f64 = WGS84_A / (1.0 - WGS84_E2*sin_phi*sin_phi).sqrt();
+		// This form of the height is well behaved near the poles, unlike p/cos(phi) - n
+		let height_m:f64 = p*cos_phi + (self.z_m + WGS84_E2*n*sin_phi)*sin_phi - n;
+
+		(phi.to_degrees(), lam.to_degrees(), height_m)
+	}
+
+	// Position relative to a reference point given by its latitude, longitude, and height above the ellipsoid
+	pub fn to_enu(&self, ref_lat_deg:f64, ref_lon_deg:f64, ref_height_m:f64) -> Enu {
+		let origin = Ecef::from_geodetic(ref_lat_deg, ref_lon_deg, ref_height_m);
+		let (dx, dy, dz) = (self.x_m - origin.x_m, self.y_m - origin.y_m, self.z_m - origin.z_m);
+		let (sin_phi, cos_phi) = ref_lat_deg.to_radians().sin_cos();
+		let (sin_lam, cos_lam) = ref_lon_deg.to_radians().sin_cos();
+
+		Enu {
+			east_m:  -sin_lam*dx + cos_lam*dy,
+			north_m: -sin_phi*cos_lam*dx - sin_phi*sin_lam*dy + cos_phi*dz,
+			up_m:     cos_phi*cos_lam*dx + cos_phi*sin_lam*dy + sin_phi*dz,
+		}
+	}
+
+}
+
+impl Enu {
+
+	// Position of the second point relative to the first, with heights above the ellipsoid
+	pub fn between(ref_lat_deg:f64, ref_lon_deg:f64, ref_height_m:f64, lat_deg:f64, lon_deg:f64, height_m:f64) -> Enu {
+		Ecef::from_geodetic(lat_deg, lon_deg, height_m).to_enu(ref_lat_deg, ref_lon_deg, ref_height_m)
+	}
+
+	// The inverse of Ecef::to_enu
+	pub fn to_ecef(&self, ref_lat_deg:f64, ref_lon_deg:f64, ref_height_m:f64) -> Ecef {
+		let origin = Ecef::from_geodetic(ref_lat_deg, ref_lon_deg, ref_height_m);
+		let (sin_phi, cos_phi) = ref_lat_deg.to_radians().sin_cos();
+		let (sin_lam, cos_lam) = ref_lon_deg.to_radians().sin_cos();
+
+		Ecef {
+			x_m: origin.x_m - sin_lam*self.east_m - sin_phi*cos_lam*self.north_m + cos_phi*cos_lam*self.up_m,
+			y_m: origin.y_m + cos_lam*self.east_m - sin_phi*sin_lam*self.north_m + cos_phi*sin_lam*self.up_m,
+			z_m: origin.z_m + cos_phi*self.north_m + sin_phi*self.up_m,
+		}
+	}
+
+}
@@ -0,0 +1,83 @@
+extern crate serde;
+
+use std::io::Cursor;
+
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use serde::{Serialize, Deserialize};
+
+use crate::Gdl90Error;
+use crate::error::truncated;
+
+// Length of a heartbeat, not counting the message ID
+pub const LENGTH:usize = 6;
+
+// Heartbeat message (ID 0), described in section 3.1 of the GDL90 spec
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Heartbeat {
+	// Status byte 1
+	pub gps_position_valid: bool,
+	pub maintenance_required: bool,
+	pub ident: bool,
+	pub address_type_talkback: bool,
+	pub gps_battery_low: bool,
+	pub ratcs: bool,
+	pub uat_initialized: bool,
+
+	// Status byte 2
+	pub csa_requested: bool,
+	pub csa_not_available: bool,
+	pub utc_ok: bool,
+
+	// Seconds since 0000Z; bit 16 comes from status byte 2
+	pub timestamp_s: u32,
+
+	// Number of uplink messages received in the previous second (5 bits) and basic and long messages (10 bits)
+	pub uplink_msg_count: u8,
+	pub basic_long_msg_count: u16,
+}
+
+impl Heartbeat {
+
+	pub fn from_slice(data: &[u8]) -> Result<Heartbeat, Gdl90Error> {
+		let t = truncated(0, LENGTH, data);
+		let mut rdr = Cursor::new(data);
+		let status_byte1:u8 = rdr.read_u8().map_err(&t)?;
+		let status_byte2:u8 = rdr.read_u8().map_err(&t)?;
+		let timestamp_lsbs:u16 = rdr.read_u16::<LittleEndian>().map_err(&t)?;
+		let msg_count:u16 = rdr.read_u16::<BigEndian>().map_err(&t)?;
+
+		Ok(Heartbeat {
+			gps_position_valid:    status_byte1 & 0x80 != 0,
+			maintenance_required:  status_byte1 & 0x40 != 0,
+			ident:                 status_byte1 & 0x20 != 0,
+			address_type_talkback: status_byte1 & 0x10 != 0,
+			gps_battery_low:       status_byte1 & 0x08 != 0,
+			ratcs:                 status_byte1 & 0x04 != 0,
+			uat_initialized:       status_byte1 & 0x01 != 0,
+			csa_requested:         status_byte2 & 0x40 != 0,
+			csa_not_available:     status_byte2 & 0x20 != 0,
+			utc_ok:                status_byte2 & 0x01 != 0,
+			timestamp_s:           (((status_byte2 >> 7) as u32) << 16) | (timestamp_lsbs as u32),
+			uplink_msg_count:      (msg_count >> 11) as u8,
+			basic_long_msg_count:  msg_count & 0x03FF,
+		})
+	}
+
+	// The inverse of from_slice; reserved bits are always zero
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let flag = |b:bool, mask:u8| if b { mask } else { 0 };
+
+		let status_byte1:u8 = flag(self.gps_position_valid, 0x80) | flag(self.maintenance_required, 0x40) |
+			flag(self.ident, 0x20) | flag(self.address_type_talkback, 0x10) | flag(self.gps_battery_low, 0x08) |
+			flag(self.ratcs, 0x04) | flag(self.uat_initialized, 0x01);
+		let status_byte2:u8 = flag(self.timestamp_s & 0x10000 != 0, 0x80) | flag(self.csa_requested, 0x40) |
+			flag(self.csa_not_available, 0x20) | flag(self.utc_ok, 0x01);
+		let msg_count:u16 = ((self.uplink_msg_count.min(31) as u16) << 11) | self.basic_long_msg_count.min(1023);
+
+		let mut ans:Vec<u8> = vec![status_byte1, status_byte2];
+		ans.extend_from_slice(&((self.timestamp_s & 0xFFFF) as u16).to_le_bytes());
+		ans.extend_from_slice(&msg_count.to_be_bytes());
+		ans
+	}
+
+}
@@ -1,10 +1,21 @@
 use std::io::Cursor;
-use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
-use crate::{StratusGDL90, traffic_report, uplink_data};
+use byteorder::{BigEndian, ReadBytesExt};
+use crate::{Gdl90Error, StratusGDL90, foreflight, heartbeat, stratux, traffic_report, uat_report, uplink_data};
+use crate::error::{require, truncated};
+use crate::extension::{self, Registry};
+use crate::preprocessing::{self, Preprocessing, PreprocessingResult};
 use crate::traffic_report::TrafficReport;
 
-fn parse_error<T>(_:T) -> &'static str {
-    "Parse error"
+// Uplink and pass-through reports start with a 24-bit time of reception, LSB first, in units of 80 ns
+fn time_of_reception_ns(msg_id: u8, data: &[u8]) -> Result<u32, Gdl90Error> {
+    require(msg_id, 3, data)?;
+    let time_of_reception_raw:u32 = ((data[2] as u32) << 16) | ((data[1] as u32) << 8) | (data[0] as u32);
+    Ok(time_of_reception_raw * 80)
+}
+
+fn time_of_reception_bytes(time_of_reception_ns: u32) -> [u8; 3] {
+    let bytes = (time_of_reception_ns / 80).to_le_bytes();
+    [bytes[0], bytes[1], bytes[2]]
 }
 
 impl StratusGDL90 {
@@ -16,76 +27,287 @@ impl StratusGDL90 {
         }
     }
 
-    // A UDP packet received on port 4000
-    pub fn from_udp_packet(buff: &[u8]) -> Result<Self, &'static str> {
-        if buff.len() < 2 || buff[0] != 0x7e {
-            return Err("Failed to parse UDP packet as StratusGDL90");
+    // Gets the concrete value back from a message decoded by an extension decoder
+    pub fn extension<T: extension::Extension>(&self) -> Option<&T> {
+        match self {
+            Self::Extension{ value, .. } => value.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+
+    // A UDP packet received on port 4000.  The packet is run through the preprocessor so that escaped bytes are
+    // restored and the FCS is checked before the first complete frame is decoded
+    pub fn from_udp_packet(buff: &[u8]) -> Result<Self, Gdl90Error> {
+        Self::from_udp_packet_with(buff, &Registry::new())
+    }
+
+    pub fn from_udp_packet_with(buff: &[u8], registry: &Registry) -> Result<Self, Gdl90Error> {
+        match buff.first() {
+            Some(0x7E) => {},
+            Some(b)    => return Err(Gdl90Error::BadFlag(*b)),
+            None       => return Err(Gdl90Error::Unterminated),
+        }
+
+        let mut pp = Preprocessing::new();
+        for b in buff {
+            match pp.next_byte(*b) {
+                PreprocessingResult::Valid(msg) => return Self::from_message_with(&msg, registry),
+                PreprocessingResult::Err(e)     => return Err(e),
+                PreprocessingResult::NotReady   => {},
+            }
         }
 
-        let data: &[u8] = &buff[2..];
+        Err(Gdl90Error::Unterminated)
+    }
+
+    // A UDP datagram that may contain several back-to-back frames, which is common for Stratus, Stratux, and SkyEcho
+    // units.  Each frame gets its own result so that one corrupted frame doesn't cause the rest to be lost
+    pub fn from_udp_datagram(buff: &[u8]) -> Vec<Result<Self, Gdl90Error>> {
+        Self::from_udp_datagram_with(buff, &Registry::new())
+    }
+
+    pub fn from_udp_datagram_with(buff: &[u8], registry: &Registry) -> Vec<Result<Self, Gdl90Error>> {
+        preprocessing::process_frames(buff.iter().cloned()).into_iter()
+            .map(|frame| frame.and_then(|msg| Self::from_message_with(&msg, registry)))
+            .collect()
+    }
 
-        match buff[1] {
-            0   => {
+    // A single message with the flag bytes, byte-stuffing, and FCS already removed, i.e. the message ID followed by
+    // the message data
+    pub fn from_message(msg: &[u8]) -> Result<Self, Gdl90Error> {
+        Self::from_message_with(msg, &Registry::new())
+    }
+
+    // Like from_message, but decoders in the registry are tried before the built-in ones
+    pub fn from_message_with(msg: &[u8], registry: &Registry) -> Result<Self, Gdl90Error> {
+        if msg.is_empty() {
+            return Err(Gdl90Error::EmptyMessage);
+        }
+
+        let data: &[u8] = &msg[1..];
+
+        if let Some(result) = registry.decode(msg[0], data) {
+            return result;
+        }
+
+        match msg[0] {
+            0   => Ok(StratusGDL90::Heartbeat(heartbeat::Heartbeat::from_slice(data)?)),
+            2   => {
+                let t = truncated(2, 2, data);
                 let mut rdr = Cursor::new(data);
-                Ok(StratusGDL90::Heartbeat{
-                    status_byte1: rdr.read_u8().map_err(parse_error)?,
-                    status_byte2: rdr.read_u8().map_err(parse_error)?,
-                    timestamp:rdr.read_u16::<LittleEndian>().map_err(parse_error)?,
-                    msg_count: rdr.read_u16::<BigEndian>().map_err(parse_error)?
+                let config_byte1:u8 = rdr.read_u8().map_err(&t)?;
+                let config_byte2:u8 = rdr.read_u8().map_err(&t)?;
+                Ok(StratusGDL90::Initialization{
+                    audio_test:        config_byte1 & 0x40 != 0,
+                    audio_inhibit:     config_byte1 & 0x02 != 0,
+                    cdti_ok:           config_byte1 & 0x01 != 0,
+                    csa_audio_disable: config_byte2 & 0x02 != 0,
+                    csa_disable:       config_byte2 & 0x01 != 0,
                 })
             },
-            2   => Ok(StratusGDL90::Initialization),
             7   => {
-                let mut rdr = Cursor::new(data);
-                let tor_lsb:u8 = rdr.read_u8().map_err(parse_error)?;
-                let tor_2sb:u8 = rdr.read_u8().map_err(parse_error)?;
-                let tor_msb:u8 = rdr.read_u8().map_err(parse_error)?;
-                let time_of_reception_raw:u32 = (tor_msb as u32 * 65536) + (tor_2sb as u32 * 256) + (tor_lsb as u32);
-                let time_of_reception_ns:u32  = time_of_reception_raw * 80;
-
-                let mut buff:Vec<u8> = vec![];
-                while let Ok(b) = rdr.read_u8() { buff.push(b); }
-                let payload = uplink_data::Payload::new(buff).map_err(parse_error)?;
+                let time_of_reception_ns:u32 = time_of_reception_ns(7, data)?;
+                let payload = uplink_data::Payload::new(data[3..].to_vec())?;
                 Ok(StratusGDL90::UplinkData{ time_of_reception_ns, payload })
             },
-            9   => Ok(StratusGDL90::HeightAboveTerrain),
-            10  => Ok(StratusGDL90::OwnshipReport(traffic_report::TrafficReport::from_slice(data).map_err(parse_error)?)),
+            9   => {
+                let mut rdr = Cursor::new(data);
+                let hat_raw:i16 = rdr.read_i16::<BigEndian>().map_err(truncated(9, 2, data))?;
+                // 0x8000 means the height above terrain is invalid
+                let hat_ft = if hat_raw == i16::MIN { None } else { Some(hat_raw) };
+                Ok(StratusGDL90::HeightAboveTerrain{ hat_ft })
+            },
+            10  => {
+                require(10, traffic_report::LENGTH, data)?;
+                Ok(StratusGDL90::OwnshipReport(traffic_report::TrafficReport::from_slice(data)?))
+            },
             11  => {
+                let t = truncated(11, 4, data);
                 let mut rdr = Cursor::new(data);
-                let altitude_raw:i16 = rdr.read_i16::<BigEndian>().map_err(parse_error)?;
-                Ok(StratusGDL90::OwnshipGeometricAltitude(altitude_raw as f32 * 5.0))
+                let altitude_raw:i16 = rdr.read_i16::<BigEndian>().map_err(&t)?;
+                let metrics_raw:u16  = rdr.read_u16::<BigEndian>().map_err(&t)?;
+
+                // The vertical figure of merit is 0x7FFF when not available and 0x7FFE when it's 32766 m or more, so
+                // Some(32766) should be read as "at least 32766 m"
+                let vertical_warning:bool = metrics_raw & 0x8000 != 0;
+                let vfom_m = match metrics_raw & 0x7FFF {
+                    0x7FFF => None,
+                    vfom   => Some(vfom),
+                };
+
+                Ok(StratusGDL90::OwnshipGeometricAltitude{ geo_altitude_ft: altitude_raw as f32 * 5.0, vertical_warning, vfom_m })
+            },
+            20  => Ok(StratusGDL90::TrafficReport(traffic_report::TrafficReport::from_slice(data)?)),
+            30  => {
+                let time_of_reception_ns:u32 = time_of_reception_ns(30, data)?;
+                let report = uat_report::UatReport::from_slice(30, &data[3..])?;
+                Ok(StratusGDL90::BasicReport{ time_of_reception_ns, report })
+            },
+            31  => {
+                let time_of_reception_ns:u32 = time_of_reception_ns(31, data)?;
+                let report = uat_report::UatReport::from_slice(31, &data[3..])?;
+                Ok(StratusGDL90::LongReport{ time_of_reception_ns, report })
+            },
+            // Stratux extensions; 0x4C is shared with Levil AHRS units
+            0x4C => Ok(StratusGDL90::StratuxAhrs(stratux::Ahrs::from_slice(data)?)),
+            0x53 => Ok(StratusGDL90::StratuxStatus(stratux::Status::from_slice(data)?)),
+            0xCC => {
+                let mut rdr = Cursor::new(data);
+                let status:u8 = rdr.read_u8().map_err(truncated(0xCC, 1, data))?;
+                Ok(StratusGDL90::StratuxHeartbeat{
+                    gps_position_valid: status & 0x02 != 0,
+                    ahrs_valid:         status & 0x01 != 0,
+                    protocol_version:   status >> 2,
+                })
             },
-            20  => Ok(StratusGDL90::TrafficReport(traffic_report::TrafficReport::from_slice(&data).map_err(parse_error)?)),
-            30  => Ok(StratusGDL90::BasicReport),
-            31  => Ok(StratusGDL90::LongReport),
             101 => {
                 let mut rdr = Cursor::new(data);
-                match rdr.read_u8().map_err(parse_error)? {
-                    0  => Ok(StratusGDL90::DeviceId),
+                match rdr.read_u8().map_err(truncated(101, 1, data))? {
+                    0  => Ok(StratusGDL90::DeviceId(foreflight::DeviceId::from_slice(data)?)),
                     1  => {
-                        let roll_raw:i16  = rdr.read_i16::<BigEndian>().map_err(parse_error)?;
-                        let pitch_raw:i16 = rdr.read_i16::<BigEndian>().map_err(parse_error)?;
-                        let hdg_raw:u16   = rdr.read_u16::<BigEndian>().map_err(parse_error)?;
-                        let ias_raw:u16   = rdr.read_u16::<BigEndian>().map_err(parse_error)?;
-                        let tas_raw:u16   = rdr.read_u16::<BigEndian>().map_err(parse_error)?;
+                        let t = truncated(101, 11, data);
+                        let roll_raw:i16  = rdr.read_i16::<BigEndian>().map_err(&t)?;
+                        let pitch_raw:i16 = rdr.read_i16::<BigEndian>().map_err(&t)?;
+                        let hdg_raw:u16   = rdr.read_u16::<BigEndian>().map_err(&t)?;
+                        let ias_raw:u16   = rdr.read_u16::<BigEndian>().map_err(&t)?;
+                        let tas_raw:u16   = rdr.read_u16::<BigEndian>().map_err(&t)?;
 
                         // Interpret raw values and check for errors
-                        let roll_deg  = if roll_raw  < -1800 || roll_raw  > 1800 { None } else { Some((roll_raw  as f32) * 0.1) };
-                        let pitch_deg = if pitch_raw < -1800 || pitch_raw > 1800 { None } else { Some((pitch_raw as f32) * 0.1) };
+                        let roll_deg  = if (-1800..=1800).contains(&roll_raw)  { Some((roll_raw  as f32) * 0.1) } else { None };
+                        let pitch_deg = if (-1800..=1800).contains(&pitch_raw) { Some((pitch_raw as f32) * 0.1) } else { None };
 
+                        // The MSB selects true or magnetic heading and the remaining 15 bits are a signed heading in
+                        // tenths of a degree, which is reported here between 0 and 360
                         let hdg_is_true:bool = hdg_raw & 0x8000 == 0;
-                        // TODO: decode heading; will involve bit shifting and u15 to i15 conversion
+                        let hdg_deg = if hdg_raw == 0xFFFF { None } else {
+                            let hdg_i15:i16 = ((hdg_raw << 1) as i16) >> 1;
+                            Some(((hdg_i15 as f32) * 0.1).rem_euclid(360.0))
+                        };
 
                         let ias_kts = if ias_raw == 0xFFFF { None } else { Some(ias_raw) };
                         let tas_kts = if tas_raw == 0xFFFF { None } else { Some(tas_raw) };
 
-                        Ok(StratusGDL90::Attitude{ roll_deg, pitch_deg, hdg_is_true, ias_kts, tas_kts })
+                        // The ForeFlight spec ends the message here, so any trailing bytes are ignored
+                        Ok(StratusGDL90::Attitude{ roll_deg, pitch_deg, hdg_deg, hdg_is_true, ias_kts, tas_kts })
                     },
-                    _  => Err("Unknown sub-ID for message 101, defined in the Foreflight extended spec"),
+                    // Sub-IDs for message 101 are defined in the Foreflight extended spec
+                    sub_id => Err(Gdl90Error::UnknownSubId{ msg_id: 101, sub_id }),
                 }
             },
-            _   => Ok(StratusGDL90::Unknown{ id: buff[1], data: data.to_vec() }),
+            _   => Ok(StratusGDL90::Unknown{ id: msg[0], data: data.to_vec() }),
+        }
+    }
+
+    // The message ID followed by the message data; the inverse of from_message
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Heartbeat(h) => {
+                let mut ans = vec![0];
+                ans.extend(h.to_bytes());
+                ans
+            },
+            Self::Initialization{ audio_test, audio_inhibit, cdti_ok, csa_audio_disable, csa_disable } => {
+                let flag = |b: &bool, mask: u8| if *b { mask } else { 0 };
+                vec![2,
+                    flag(audio_test, 0x40) | flag(audio_inhibit, 0x02) | flag(cdti_ok, 0x01),
+                    flag(csa_audio_disable, 0x02) | flag(csa_disable, 0x01)]
+            },
+            Self::UplinkData{ time_of_reception_ns, payload } => {
+                let mut ans = vec![7];
+                ans.extend_from_slice(&time_of_reception_bytes(*time_of_reception_ns));
+                ans.extend(payload.to_bytes());
+                ans
+            },
+            Self::HeightAboveTerrain{ hat_ft } => {
+                let mut ans = vec![9];
+                ans.extend_from_slice(&hat_ft.unwrap_or(i16::MIN).to_be_bytes());
+                ans
+            },
+            Self::OwnshipReport(r) => {
+                let mut ans = vec![10];
+                ans.extend(r.to_bytes());
+                ans
+            },
+            Self::OwnshipGeometricAltitude{ geo_altitude_ft, vertical_warning, vfom_m } => {
+                let metrics_raw: u16 = (if *vertical_warning { 0x8000 } else { 0 }) | vfom_m.map(|v| v.min(0x7FFE)).unwrap_or(0x7FFF);
+
+                let mut ans = vec![11];
+                ans.extend_from_slice(&((geo_altitude_ft / 5.0).round() as i16).to_be_bytes());
+                ans.extend_from_slice(&metrics_raw.to_be_bytes());
+                ans
+            },
+            Self::TrafficReport(r) => {
+                let mut ans = vec![20];
+                ans.extend(r.to_bytes());
+                ans
+            },
+            Self::BasicReport{ time_of_reception_ns, report } => {
+                let mut ans = vec![30];
+                ans.extend_from_slice(&time_of_reception_bytes(*time_of_reception_ns));
+                ans.extend(report.to_bytes());
+                ans
+            },
+            Self::LongReport{ time_of_reception_ns, report } => {
+                let mut ans = vec![31];
+                ans.extend_from_slice(&time_of_reception_bytes(*time_of_reception_ns));
+                ans.extend(report.to_bytes());
+                ans
+            },
+            Self::DeviceId(id) => {
+                let mut ans = vec![101];
+                ans.extend(id.to_bytes());
+                ans
+            },
+            Self::Unknown{ id, data } => {
+                let mut ans = vec![*id];
+                ans.extend_from_slice(data);
+                ans
+            },
+            Self::Extension{ id, value } => {
+                let mut ans = vec![*id];
+                ans.extend(value.to_bytes());
+                ans
+            },
+            Self::StratuxHeartbeat{ gps_position_valid, ahrs_valid, protocol_version } => {
+                let flag = |b: &bool, mask: u8| if *b { mask } else { 0 };
+                vec![0xCC, (protocol_version << 2) | flag(gps_position_valid, 0x02) | flag(ahrs_valid, 0x01)]
+            },
+            Self::StratuxAhrs(ahrs) => {
+                let mut ans = vec![0x4C];
+                ans.extend(ahrs.to_bytes());
+                ans
+            },
+            Self::StratuxStatus(status) => {
+                let mut ans = vec![0x53];
+                ans.extend(status.to_bytes());
+                ans
+            },
+            Self::Attitude{ roll_deg, pitch_deg, hdg_deg, hdg_is_true, ias_kts, tas_kts } => {
+                let angle = |x: &Option<f32>| x.map(|deg| (deg * 10.0).round() as i16).unwrap_or(0x7FFF);
+
+                // An invalid heading is all ones regardless of whether it's true or magnetic
+                let hdg_raw: u16 = match hdg_deg {
+                    Some(deg) => {
+                        let hdg_i15 = ((deg.rem_euclid(360.0) * 10.0).round() as u16) & 0x7FFF;
+                        if *hdg_is_true { hdg_i15 } else { hdg_i15 | 0x8000 }
+                    },
+                    None => 0xFFFF,
+                };
+
+                let mut ans = vec![101, 1];
+                ans.extend_from_slice(&angle(roll_deg).to_be_bytes());
+                ans.extend_from_slice(&angle(pitch_deg).to_be_bytes());
+                ans.extend_from_slice(&hdg_raw.to_be_bytes());
+                ans.extend_from_slice(&ias_kts.unwrap_or(0xFFFF).to_be_bytes());
+                ans.extend_from_slice(&tas_kts.unwrap_or(0xFFFF).to_be_bytes());
+                ans
+            },
         }
     }
 
-}
\ No newline at end of file
+    // A complete frame ready to be sent in a UDP packet, including the flag bytes, FCS, and byte-stuffing
+    pub fn encode_frame(&self) -> Vec<u8> {
+        preprocessing::encode_frame(&self.to_bytes())
+    }
+
+}
@@ -0,0 +1,174 @@
+extern crate serde;
+
+use serde::{Serialize, Deserialize};
+
+use crate::METERS_PER_NM;
+
+// Navigation Integrity Category: the horizontal containment radius (Rc) that the reported position is guaranteed to
+// be within.  Values come from table 9 of the GDL90 spec, which follows DO-260B.  There's no ordering, since reserved
+// values say nothing about integrity; compare containment radii instead
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Nic {
+	Unknown,
+	Rc20Nm,
+	Rc8Nm,
+	Rc4Nm,
+	Rc2Nm,
+	Rc1Nm,
+	Rc0_6Nm,
+	Rc0_2Nm,
+	Rc0_1Nm,
+	Rc75M,
+	Rc25M,
+	Rc7_5M,
+	Reserved(u8),
+}
+
+// Navigation Accuracy Category for Position: the 95% bound on the estimated position uncertainty (EPU).  Values come
+// from table 10 of the GDL90 spec.  Like Nic, compare uncertainties rather than categories
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Nacp {
+	Unknown,
+	Epu10Nm,
+	Epu4Nm,
+	Epu2Nm,
+	Epu1Nm,
+	Epu0_5Nm,
+	Epu0_3Nm,
+	Epu0_1Nm,
+	Epu0_05Nm,
+	Epu30M,
+	Epu10M,
+	Epu3M,
+	Reserved(u8),
+}
+
+impl Nic {
+
+	pub fn from_u8(x:u8) -> Nic {
+		match x {
+			0  => Nic::Unknown,
+			1  => Nic::Rc20Nm,
+			2  => Nic::Rc8Nm,
+			3  => Nic::Rc4Nm,
+			4  => Nic::Rc2Nm,
+			5  => Nic::Rc1Nm,
+			6  => Nic::Rc0_6Nm,
+			7  => Nic::Rc0_2Nm,
+			8  => Nic::Rc0_1Nm,
+			9  => Nic::Rc75M,
+			10 => Nic::Rc25M,
+			11 => Nic::Rc7_5M,
+			x  => Nic::Reserved(x),
+		}
+	}
+
+	pub fn to_u8(self) -> u8 {
+		match self {
+			Nic::Unknown     => 0,
+			Nic::Rc20Nm      => 1,
+			Nic::Rc8Nm       => 2,
+			Nic::Rc4Nm       => 3,
+			Nic::Rc2Nm       => 4,
+			Nic::Rc1Nm       => 5,
+			Nic::Rc0_6Nm     => 6,
+			Nic::Rc0_2Nm     => 7,
+			Nic::Rc0_1Nm     => 8,
+			Nic::Rc75M       => 9,
+			Nic::Rc25M       => 10,
+			Nic::Rc7_5M      => 11,
+			Nic::Reserved(x) => x,
+		}
+	}
+
+	// None when the containment radius is unknown or the value is reserved
+	pub fn containment_radius_m(self) -> Option<f32> {
+		match self {
+			Nic::Rc75M  => Some(75.0),
+			Nic::Rc25M  => Some(25.0),
+			Nic::Rc7_5M => Some(7.5),
+			_           => self.containment_radius_nm().map(|nm| nm * METERS_PER_NM),
+		}
+	}
+
+	pub fn containment_radius_nm(self) -> Option<f32> {
+		match self {
+			Nic::Rc20Nm  => Some(20.0),
+			Nic::Rc8Nm   => Some(8.0),
+			Nic::Rc4Nm   => Some(4.0),
+			Nic::Rc2Nm   => Some(2.0),
+			Nic::Rc1Nm   => Some(1.0),
+			Nic::Rc0_6Nm => Some(0.6),
+			Nic::Rc0_2Nm => Some(0.2),
+			Nic::Rc0_1Nm => Some(0.1),
+			Nic::Rc75M | Nic::Rc25M | Nic::Rc7_5M => self.containment_radius_m().map(|m| m / METERS_PER_NM),
+			Nic::Unknown | Nic::Reserved(_) => None,
+		}
+	}
+
+}
+
+impl Nacp {
+
+	pub fn from_u8(x:u8) -> Nacp {
+		match x {
+			0  => Nacp::Unknown,
+			1  => Nacp::Epu10Nm,
+			2  => Nacp::Epu4Nm,
+			3  => Nacp::Epu2Nm,
+			4  => Nacp::Epu1Nm,
+			5  => Nacp::Epu0_5Nm,
+			6  => Nacp::Epu0_3Nm,
+			7  => Nacp::Epu0_1Nm,
+			8  => Nacp::Epu0_05Nm,
+			9  => Nacp::Epu30M,
+			10 => Nacp::Epu10M,
+			11 => Nacp::Epu3M,
+			x  => Nacp::Reserved(x),
+		}
+	}
+
+	pub fn to_u8(self) -> u8 {
+		match self {
+			Nacp::Unknown     => 0,
+			Nacp::Epu10Nm     => 1,
+			Nacp::Epu4Nm      => 2,
+			Nacp::Epu2Nm      => 3,
+			Nacp::Epu1Nm      => 4,
+			Nacp::Epu0_5Nm    => 5,
+			Nacp::Epu0_3Nm    => 6,
+			Nacp::Epu0_1Nm    => 7,
+			Nacp::Epu0_05Nm   => 8,
+			Nacp::Epu30M      => 9,
+			Nacp::Epu10M      => 10,
+			Nacp::Epu3M       => 11,
+			Nacp::Reserved(x) => x,
+		}
+	}
+
+	// None when the position uncertainty is unknown or the value is reserved
+	pub fn epu_m(self) -> Option<f32> {
+		match self {
+			Nacp::Epu30M => Some(30.0),
+			Nacp::Epu10M => Some(10.0),
+			Nacp::Epu3M  => Some(3.0),
+			_            => self.epu_nm().map(|nm| nm * METERS_PER_NM),
+		}
+	}
+
+	pub fn epu_nm(self) -> Option<f32> {
+		match self {
+			Nacp::Epu10Nm   => Some(10.0),
+			Nacp::Epu4Nm    => Some(4.0),
+			Nacp::Epu2Nm    => Some(2.0),
+			Nacp::Epu1Nm    => Some(1.0),
+			Nacp::Epu0_5Nm  => Some(0.5),
+			Nacp::Epu0_3Nm  => Some(0.3),
+			Nacp::Epu0_1Nm  => Some(0.1),
+			Nacp::Epu0_05Nm => Some(0.05),
+			Nacp::Epu30M | Nacp::Epu10M | Nacp::Epu3M => self.epu_m().map(|m| m / METERS_PER_NM),
+			Nacp::Unknown | Nacp::Reserved(_) => None,
+		}
+	}
+
+}
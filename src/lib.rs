@@ -9,18 +9,34 @@ const RAD_PER_DEG:f32 = std::f32::consts::PI / 180.0;
 const METERS_PER_NM:f32 = 1852.0;
 const FEET_PER_NM:f32   = 6076.12;
 
-const R:f32 = 6.371e6;
+// The error type shared by all of the decoders
+pub mod error;
+pub use error::Gdl90Error;
+
+// Decoders for vendor-specific messages supplied by other crates
+pub mod extension;
 
 // Preprocessing steps that need to be applied to raw UDP packets before being consumed by the
 // rest of the library
 pub mod preprocessing;
 
 // Modules related to messages that are complicated enough to require their own modules
+pub mod foreflight;
+pub mod heartbeat;
+pub mod integrity;
+pub mod stratux;
 pub mod traffic_report;
+pub mod traffic_table;
+pub mod alerting;
+pub mod ownship;
+pub mod uat_report;
 pub mod uplink_data;
 
 pub mod metar;
 
+// Double-precision distances, bearings, and coordinate conversions on a sphere or on WGS-84
+pub mod geodesy;
+
 // Useful utilities that aren't really GDL90-specific, but are needed in more than one place
 pub mod util;
 
@@ -33,17 +49,23 @@ mod impl_stratus_gdl90;
 // transmitted by Stratus units and used by ForeFlight
 #[derive(Debug, Serialize, Deserialize)]
 pub enum StratusGDL90 {
-	Heartbeat{status_byte1:u8, status_byte2:u8, timestamp:u16, msg_count:u16},
-	Initialization,
+	Heartbeat(heartbeat::Heartbeat),
+	Initialization{ audio_test:bool, audio_inhibit:bool, cdti_ok:bool, csa_audio_disable:bool, csa_disable:bool },
 	UplinkData{ time_of_reception_ns:u32, payload:uplink_data::Payload },
-	HeightAboveTerrain,
+	HeightAboveTerrain{ hat_ft:Option<i16> },
 	OwnshipReport(traffic_report::TrafficReport),
-	OwnshipGeometricAltitude(f32),
+	OwnshipGeometricAltitude{ geo_altitude_ft:f32, vertical_warning:bool, vfom_m:Option<u16> },
 	TrafficReport(traffic_report::TrafficReport),
-	BasicReport,
-	LongReport,
+	BasicReport{ time_of_reception_ns:u32, report:uat_report::UatReport },
+	LongReport{ time_of_reception_ns:u32, report:uat_report::UatReport },
 	Unknown{ id:u8, data:Vec<u8> },
-	DeviceId,
-	Attitude{ roll_deg:Option<f32>, pitch_deg:Option<f32>, hdg_is_true:bool, ias_kts:Option<u16>, tas_kts:Option<u16> },
+	DeviceId(foreflight::DeviceId),
+	StratuxHeartbeat{ gps_position_valid:bool, ahrs_valid:bool, protocol_version:u8 },
+	StratuxAhrs(stratux::Ahrs),
+	StratuxStatus(stratux::Status),
+	Attitude{ roll_deg:Option<f32>, pitch_deg:Option<f32>, hdg_deg:Option<f32>, hdg_is_true:bool, ias_kts:Option<u16>, tas_kts:Option<u16> },
+	// Decoded by a decoder from an extension::Registry; these can't be serialized
+	#[serde(skip)]
+	Extension{ id:u8, value:Box<dyn extension::Extension> },
 }
 
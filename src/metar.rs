@@ -6,6 +6,8 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Serialize, Deserialize};
 
+use crate::Gdl90Error;
+
 // These fields are mandatory, which is why there are no ? qualifiers after the groups
 //                              (  1  )  (  2  )(  3  )(  4  )
 const STATION_AND_TIME:&str = r"(\S{4})\s(\d{2})(\d{2})(\d{2})Z";
@@ -80,13 +82,13 @@ pub struct METAR {
 
 impl METAR {
 
-	pub fn from_string(s:&str) -> Result<METAR, &str> {
+	pub fn from_string(s:&str) -> Result<METAR, Gdl90Error> {
 		if let Some(caps) = METAR_RE.captures(s) { 
 			// Field that are always present; these will return an Err if they aren't found
-			let station:&str = caps.get(1).map(|m| m.as_str()).ok_or("No match for station ")?;
-			let day:u8       = caps.get(2).map_or("", |m| m.as_str()).parse::<u8>().map_err(|_| "Unable to parse u8")?;
-			let hour:u8      = caps.get(3).map_or("", |m| m.as_str()).parse::<u8>().map_err(|_| "Unable to parse u8")?;
-			let min:u8       = caps.get(4).map_or("", |m| m.as_str()).parse::<u8>().map_err(|_| "Unable to parse u8")?;
+			let station:&str = caps.get(1).map(|m| m.as_str()).ok_or(Gdl90Error::Unrecognized("METAR station"))?;
+			let day:u8       = caps.get(2).map_or("", |m| m.as_str()).parse::<u8>().map_err(|_| Gdl90Error::Unrecognized("METAR time"))?;
+			let hour:u8      = caps.get(3).map_or("", |m| m.as_str()).parse::<u8>().map_err(|_| Gdl90Error::Unrecognized("METAR time"))?;
+			let min:u8       = caps.get(4).map_or("", |m| m.as_str()).parse::<u8>().map_err(|_| Gdl90Error::Unrecognized("METAR time"))?;
 
 			// Fields that are not always present; these are represented as some kind of optional type in the struct
 			let quality_control_flags = match caps.get(5).map_or("", |m| m.as_str()) {
@@ -119,7 +121,7 @@ impl METAR {
 
 			let temperature:Option<f32> = caps.get(37).map(|m| m.as_str()).map(|temp_grp| {
 				if &temp_grp[..1] == "M" {
-					(temp_grp[1..]).parse::<f32>().map(|x| x * -1.0).ok()
+					(temp_grp[1..]).parse::<f32>().map(|x| -x).ok()
 				} else {
 					temp_grp.parse::<f32>().ok()
 				}
@@ -127,7 +129,7 @@ impl METAR {
 
 			let dew_point:Option<f32> = caps.get(38).map(|m| m.as_str()).map(|temp_grp| {
 				if &temp_grp[..1] == "M" {
-					(temp_grp[1..]).parse::<f32>().map(|x| x * -1.0).ok()
+					(temp_grp[1..]).parse::<f32>().map(|x| -x).ok()
 				} else {
 					temp_grp.parse::<f32>().ok()
 				}
@@ -146,7 +148,7 @@ impl METAR {
 							visibility_sm, sky_condition, altimeter, temperature, dew_point })
 		}
 		else {
-			Err("Unable to match the text to the METAR regex")
+			Err(Gdl90Error::Unrecognized("METAR"))
 		}
 	}
 
@@ -0,0 +1,168 @@
+extern crate serde;
+
+use std::time::{Duration, SystemTime};
+
+use serde::{Serialize, Deserialize};
+
+use crate::StratusGDL90;
+use crate::foreflight::AltitudeDatum;
+use crate::heartbeat::Heartbeat;
+use crate::traffic_report::TrafficReport;
+
+// Roughly 1000 ft of altitude per inch of mercury near sea level
+const FEET_PER_INHG:f32 = 1000.0;
+const STANDARD_INHG:f32 = 29.92;
+
+// A value along with the time that it was received
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Timestamped<T> {
+	pub value: T,
+	pub time: SystemTime,
+}
+
+impl<T> Timestamped<T> {
+
+	pub fn age(&self, now:SystemTime) -> Duration {
+		now.duration_since(self.time).unwrap_or(Duration::from_secs(0))
+	}
+
+	pub fn is_stale(&self, max_age:Duration, now:SystemTime) -> bool {
+		self.age(now) > max_age
+	}
+
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct GeometricAltitude {
+	pub geo_altitude_ft: f32,
+	pub vertical_warning: bool,
+	pub vfom_m: Option<u16>,
+}
+
+// Attitude from either the ForeFlight attitude message or the Stratux AHRS message.  Fields that the source doesn't
+// provide are None
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Attitude {
+	pub roll_deg: Option<f32>,
+	pub pitch_deg: Option<f32>,
+	pub hdg_deg: Option<f32>,
+	pub hdg_is_true: bool,
+	pub slip_skid_deg: Option<f32>,
+	pub yaw_rate_dps: Option<f32>,
+	pub g_load: Option<f32>,
+	pub ias_kts: Option<f32>,
+	pub tas_kts: Option<f32>,
+}
+
+// Everything known about ownship, put together from the messages that describe it.  Each part is None until a message
+// providing it has been received, and keeps the time it was last updated so that the caller can decide when it's too
+// old to use
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OwnshipState {
+	pub heartbeat: Option<Timestamped<Heartbeat>>,
+	// From the standard heartbeat or the Stratux heartbeat, whichever was received last
+	pub gps_position_valid: Option<Timestamped<bool>>,
+	pub report: Option<Timestamped<TrafficReport>>,
+	pub geo_altitude: Option<Timestamped<GeometricAltitude>>,
+	// From the ForeFlight ID message.  Standard GDL90 uses the WGS-84 ellipsoid
+	pub geo_altitude_datum: Option<AltitudeDatum>,
+	// The inner value is None when the device says that the height above terrain isn't valid
+	pub hat_ft: Option<Timestamped<Option<i16>>>,
+	pub attitude: Option<Timestamped<Attitude>>,
+}
+
+impl OwnshipState {
+
+	pub fn new() -> OwnshipState {
+		OwnshipState::default()
+	}
+
+	// Updates the state from a message received now.  Returns false if the message doesn't describe ownship
+	pub fn update(&mut self, msg:&StratusGDL90) -> bool {
+		self.update_at(msg, SystemTime::now())
+	}
+
+	pub fn update_at(&mut self, msg:&StratusGDL90, time:SystemTime) -> bool {
+		match msg {
+			StratusGDL90::Heartbeat(heartbeat) => {
+				self.heartbeat = Some(Timestamped{ value: *heartbeat, time });
+				self.gps_position_valid = Some(Timestamped{ value: heartbeat.gps_position_valid, time });
+			},
+			StratusGDL90::StratuxHeartbeat{ gps_position_valid, .. } => {
+				self.gps_position_valid = Some(Timestamped{ value: *gps_position_valid, time });
+			},
+			StratusGDL90::OwnshipReport(report) => {
+				self.report = Some(Timestamped{ value: report.clone(), time });
+			},
+			StratusGDL90::OwnshipGeometricAltitude{ geo_altitude_ft, vertical_warning, vfom_m } => {
+				let value = GeometricAltitude{ geo_altitude_ft: *geo_altitude_ft, vertical_warning: *vertical_warning, vfom_m: *vfom_m };
+				self.geo_altitude = Some(Timestamped{ value, time });
+			},
+			StratusGDL90::HeightAboveTerrain{ hat_ft } => {
+				self.hat_ft = Some(Timestamped{ value: *hat_ft, time });
+			},
+			StratusGDL90::DeviceId(device_id) => {
+				self.geo_altitude_datum = Some(device_id.geometric_altitude_datum);
+			},
+			StratusGDL90::Attitude{ roll_deg, pitch_deg, hdg_deg, hdg_is_true, ias_kts, tas_kts } => {
+				let value = Attitude {
+					roll_deg: *roll_deg, pitch_deg: *pitch_deg, hdg_deg: *hdg_deg, hdg_is_true: *hdg_is_true,
+					slip_skid_deg: None, yaw_rate_dps: None, g_load: None,
+					ias_kts: ias_kts.map(|x| x as f32),
+					tas_kts: tas_kts.map(|x| x as f32),
+				};
+				self.attitude = Some(Timestamped{ value, time });
+			},
+			StratusGDL90::StratuxAhrs(ahrs) => {
+				// Stratux doesn't say whether the heading is true or magnetic, but it comes from a magnetometer
+				let value = Attitude {
+					roll_deg: ahrs.roll_deg, pitch_deg: ahrs.pitch_deg, hdg_deg: ahrs.hdg_deg, hdg_is_true: false,
+					slip_skid_deg: ahrs.slip_skid_deg, yaw_rate_dps: ahrs.yaw_rate_dps, g_load: ahrs.g_load,
+					ias_kts: ahrs.ias_kts.map(|x| x as f32),
+					tas_kts: None,
+				};
+				self.attitude = Some(Timestamped{ value, time });
+			},
+			_ => return false,
+		}
+		true
+	}
+
+	// Latitude and longitude from the most recent ownship report
+	pub fn position(&self) -> Option<(f32, f32)> {
+		let report = &self.report.as_ref()?.value;
+		Some((report.latitude_deg?, report.longitude_deg?))
+	}
+
+	pub fn pres_altitude_ft(&self) -> Option<f32> {
+		self.report.as_ref()?.value.pres_altitude_ft
+	}
+
+	pub fn geo_altitude_ft(&self) -> Option<f32> {
+		Some(self.geo_altitude.as_ref()?.value.geo_altitude_ft)
+	}
+
+	// Geometric altitude minus pressure altitude
+	pub fn altitude_difference_ft(&self) -> Option<f32> {
+		Some(self.geo_altitude_ft()? - self.pres_altitude_ft()?)
+	}
+
+	// The altimeter setting that would make the pressure altitude read the geometric altitude.  This is only an
+	// estimate, since it ignores temperature.  None unless the device has said that its geometric altitude is above
+	// mean sea level, since the height of the geoid above the ellipsoid would add up to about 0.1 inHg of error
+	pub fn derived_altimeter_setting_inhg(&self) -> Option<f32> {
+		if self.geo_altitude_datum != Some(AltitudeDatum::MeanSeaLevel) {
+			return None;
+		}
+		Some(STANDARD_INHG + self.altitude_difference_ft()? / FEET_PER_INHG)
+	}
+
+	pub fn gps_position_valid(&self) -> Option<bool> {
+		Some(self.gps_position_valid.as_ref()?.value)
+	}
+
+	pub fn attitude(&self) -> Option<&Attitude> {
+		Some(&self.attitude.as_ref()?.value)
+	}
+
+}
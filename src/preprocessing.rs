@@ -1,8 +1,5 @@
 
-extern crate byteorder;
-
-use std::io::Cursor;
-use byteorder::{BigEndian, ReadBytesExt};
+use crate::Gdl90Error;
 
 pub mod frame_check_seq {
 	const CRC_CCITT_TABLE:[u16; 256] = [0x0000, 0x1021, 0x2042, 0x3063, 0x4084, 0x50A5, 0x60C6, 0x70E7,
@@ -38,7 +35,7 @@ pub mod frame_check_seq {
 	    0xEF1F, 0xFF3E, 0xCF5D, 0xDF7C, 0xAF9B, 0xBFBA, 0x8FD9, 0x9FF8,
 	    0x6E17, 0x7E36, 0x4E55, 0x5E74, 0x2E93, 0x3EB2, 0x0ED1, 0x1EF0];
 
-	pub fn get_crc(data:&Vec<u8>) -> u16 {
+	pub fn get_crc(data:&[u8]) -> u16 {
 		let mut crc:u16 = 0;
 
 		for d in data {
@@ -57,10 +54,14 @@ pub struct Preprocessing {
 #[derive(Debug)]
 pub enum PreprocessingResult {
 	NotReady,
-	Err(&'static str),
+	Err(Gdl90Error),
 	Valid(Vec<u8>)
 }
 
+impl Default for Preprocessing {
+	fn default() -> Self { Self::new() }
+}
+
 impl Preprocessing {
 
 	pub fn new() -> Preprocessing {
@@ -73,22 +74,15 @@ impl Preprocessing {
 			// Message boundary flag
 			if let (Some(fcs_msb), Some(fcs_lsb)) = (self.buffer.pop(), self.buffer.pop()) {
 				// If the buffer is at least two bytes long, treat the last two bytes as the FCS
-				if let Ok(mesg_fcs) = Cursor::new(vec![fcs_msb, fcs_lsb]).read_u16::<BigEndian>() {
-					let calc_fcs:u16 = frame_check_seq::get_crc(&self.buffer);
-					self.escaped = false;
+				let mesg_fcs:u16 = ((fcs_msb as u16) << 8) | (fcs_lsb as u16);
+				let calc_fcs:u16 = frame_check_seq::get_crc(&self.buffer);
+				self.escaped = false;
 
-					if mesg_fcs == calc_fcs {
-						PreprocessingResult::Valid(self.buffer.drain(..).collect())
-					} else {
-						self.buffer.clear();
-						PreprocessingResult::NotReady
-					}
+				if mesg_fcs == calc_fcs {
+					PreprocessingResult::Valid(self.buffer.drain(..).collect())
 				} else {
-					// We have two bytes, but somehow weren't able to interpret them as a u16.  This should never happen,
-					// but the possibility is included for completeness
 					self.buffer.clear();
-					self.escaped = false;
-					PreprocessingResult::Err("Unable to interpret two FCS bytes as a u16")
+					PreprocessingResult::Err(Gdl90Error::CrcMismatch{ expected: calc_fcs, actual: mesg_fcs })
 				}
 			} else {
 				// We don't even have the two FCS bytes available.  This is probably because the buffer was empty
@@ -103,12 +97,13 @@ impl Preprocessing {
 				PreprocessingResult::NotReady
 			} else {
 				if self.escaped {
-					// TODO: check that b is one of the two special characters that need to be escaped
+					// Only 0x7E and 0x7D are ever escaped; anything else means the frame is corrupt, which
+					// will be caught by the FCS check when the closing flag arrives
 					self.buffer.push(b ^ 0x20);
 					self.escaped = false;
 				} else {
 					self.buffer.push(b);
-				}			
+				}
 				PreprocessingResult::NotReady
 			}
 		}
@@ -117,15 +112,46 @@ impl Preprocessing {
 }
 
 pub fn process<T: Iterator<Item=u8>>(iter:T) -> Vec<Vec<u8>> {
-	let mut ans:Vec<Vec<u8>> = vec![];
+	process_frames(iter).into_iter().filter_map(|r| r.ok()).collect()
+}
+
+// Like process, but frames that fail the FCS check or have no closing flag are kept as errors in the order they were
+// received instead of being dropped, so the caller can tell how many frames were lost and why
+pub fn process_frames<T: Iterator<Item=u8>>(iter:T) -> Vec<Result<Vec<u8>, Gdl90Error>> {
+	let mut ans:Vec<Result<Vec<u8>, Gdl90Error>> = vec![];
 	let mut pp:Preprocessing = Preprocessing::new();
-	
+
 	for b in iter {
 		match pp.next_byte(b) {
-			PreprocessingResult::Valid(data) => ans.push(data),
-			_ => {},
+			PreprocessingResult::Valid(data) => ans.push(Ok(data)),
+			PreprocessingResult::Err(e)      => ans.push(Err(e)),
+			PreprocessingResult::NotReady    => {},
 		}
 	}
 
+	// Bytes after the last flag are a frame that was cut off
+	if !pp.buffer.is_empty() || pp.escaped {
+		ans.push(Err(Gdl90Error::Unterminated));
+	}
+
+	ans
+}
+
+// The inverse of the preprocessing steps: appends the FCS (LSB first) to a message, escapes any flag or
+// control-escape bytes, and wraps the result in flag bytes
+pub fn encode_frame(msg:&[u8]) -> Vec<u8> {
+	let fcs:u16 = frame_check_seq::get_crc(msg);
+	let mut ans:Vec<u8> = vec![0x7E];
+
+	for b in msg.iter().chain(fcs.to_le_bytes().iter()) {
+		if *b == 0x7E || *b == 0x7D {
+			ans.push(0x7D);
+			ans.push(*b ^ 0x20);
+		} else {
+			ans.push(*b);
+		}
+	}
+
+	ans.push(0x7E);
 	ans
-}
\ No newline at end of file
+}
@@ -0,0 +1,201 @@
+extern crate serde;
+
+use std::io::Cursor;
+
+use byteorder::{BigEndian, ReadBytesExt};
+use serde::{Serialize, Deserialize};
+
+use crate::Gdl90Error;
+use crate::error::{require, truncated};
+use crate::traffic_report::LAT_LON_SCALE;
+
+// Lengths not counting the message ID
+pub const AHRS_LENGTH:usize   = 21;
+pub const STATUS_LENGTH:usize = 28;
+
+// Levil-compatible AHRS message sent by Stratux (ID 0x4C, sub-ID 0x45).  Every field is None when Stratux reports it
+// as invalid
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Ahrs {
+	pub roll_deg: Option<f32>,
+	pub pitch_deg: Option<f32>,
+	pub hdg_deg: Option<f32>,
+	pub slip_skid_deg: Option<f32>,
+	pub yaw_rate_dps: Option<f32>,
+	pub g_load: Option<f32>,
+	pub ias_kts: Option<i16>,
+	pub pres_altitude_ft: Option<i32>,
+	pub vert_velocity_fpm: Option<i16>,
+}
+
+// Stratux status message (ID 0x53, 'S')
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Status {
+	pub protocol_version: u8,
+	// Major, minor, hotfix, and build type
+	pub firmware_version: [u8; 4],
+	pub hardware_revision: Option<u32>,
+
+	// GPS solution: 0 is no fix, anything else is a valid fix with higher values for better solutions (e.g. SBAS)
+	pub gps_solution: u8,
+	pub ahrs_valid: bool,
+	pub pres_altitude_valid: bool,
+	pub uat_enabled: bool,
+	pub es_enabled: bool,
+	pub gps_enabled: bool,
+	pub ahrs_enabled: bool,
+
+	pub radio_count: u8,
+	pub satellites_locked: u8,
+	pub satellites_tracked: u8,
+	pub uat_targets: u16,
+	pub es_targets: u16,
+	pub uat_messages_per_min: u16,
+	pub es_messages_per_min: u16,
+	pub cpu_temp_c: Option<f32>,
+
+	// Latitude and longitude of each ADS-B ground station heard recently
+	pub towers: Vec<(f32, f32)>,
+}
+
+// Every angle and rate in the AHRS message is a signed value in tenths with 0x7FFF meaning invalid
+fn read_tenths(rdr:&mut Cursor<&[u8]>) -> std::io::Result<Option<f32>> {
+	let raw:i16 = rdr.read_i16::<BigEndian>()?;
+	Ok(if raw == 0x7FFF { None } else { Some(raw as f32 * 0.1) })
+}
+
+fn write_tenths(x:Option<f32>) -> [u8; 2] {
+	x.map(|v| (v * 10.0).round() as i16).unwrap_or(0x7FFF).to_be_bytes()
+}
+
+impl Ahrs {
+
+	// Decodes the message starting with the sub-ID
+	pub fn from_slice(data:&[u8]) -> Result<Ahrs, Gdl90Error> {
+		let t = truncated(0x4C, AHRS_LENGTH, data);
+		let mut rdr = Cursor::new(data);
+
+		let sub_id:u8 = rdr.read_u8().map_err(&t)?;
+		if sub_id != 0x45 {
+			return Err(Gdl90Error::UnknownSubId{ msg_id: 0x4C, sub_id });
+		}
+
+		// Message type 1 is AHRS, which is followed by the AHRS message version
+		let msg_type:u8 = rdr.read_u8().map_err(&t)?;
+		if msg_type != 0x01 {
+			return Err(Gdl90Error::InvalidField{ field: "Stratux AHRS message type", value: msg_type as u32 });
+		}
+		let _version:u8 = rdr.read_u8().map_err(&t)?;
+
+		let roll_deg      = read_tenths(&mut rdr).map_err(&t)?;
+		let pitch_deg     = read_tenths(&mut rdr).map_err(&t)?;
+		let hdg_deg       = read_tenths(&mut rdr).map_err(&t)?;
+		let slip_skid_deg = read_tenths(&mut rdr).map_err(&t)?;
+		let yaw_rate_dps  = read_tenths(&mut rdr).map_err(&t)?;
+		let g_load        = read_tenths(&mut rdr).map_err(&t)?;
+
+		let ias_raw:i16  = rdr.read_i16::<BigEndian>().map_err(&t)?;
+		let palt_raw:u16 = rdr.read_u16::<BigEndian>().map_err(&t)?;
+		let vs_raw:i16   = rdr.read_i16::<BigEndian>().map_err(&t)?;
+
+		// Pressure altitude has a 5000 ft offset so that it can be unsigned
+		let ias_kts           = if ias_raw  == 0x7FFF { None } else { Some(ias_raw) };
+		let pres_altitude_ft  = if palt_raw == 0xFFFF { None } else { Some(palt_raw as i32 - 5000) };
+		let vert_velocity_fpm = if vs_raw   == 0x7FFF { None } else { Some(vs_raw) };
+
+		Ok(Ahrs{ roll_deg, pitch_deg, hdg_deg, slip_skid_deg, yaw_rate_dps, g_load, ias_kts, pres_altitude_ft, vert_velocity_fpm })
+	}
+
+	// The inverse of from_slice, starting with the sub-ID
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut ans:Vec<u8> = vec![0x45, 0x01, 0x01];
+		for x in &[self.roll_deg, self.pitch_deg, self.hdg_deg, self.slip_skid_deg, self.yaw_rate_dps, self.g_load] {
+			ans.extend_from_slice(&write_tenths(*x));
+		}
+		ans.extend_from_slice(&self.ias_kts.unwrap_or(0x7FFF).to_be_bytes());
+		ans.extend_from_slice(&self.pres_altitude_ft.map(|ft| (ft + 5000).clamp(0, 0xFFFE) as u16).unwrap_or(0xFFFF).to_be_bytes());
+		ans.extend_from_slice(&self.vert_velocity_fpm.unwrap_or(0x7FFF).to_be_bytes());
+		ans
+	}
+
+}
+
+impl Status {
+
+	pub fn from_slice(data:&[u8]) -> Result<Status, Gdl90Error> {
+		require(0x53, STATUS_LENGTH, data)?;
+		let t = truncated(0x53, STATUS_LENGTH, data);
+		let mut rdr = Cursor::new(data);
+
+		let protocol_version:u8 = rdr.read_u8().map_err(&t)?;
+		let mut firmware_version:[u8; 4] = [0; 4];
+		for v in firmware_version.iter_mut() {
+			*v = rdr.read_u8().map_err(&t)?;
+		}
+		let hw_raw:u32 = rdr.read_u32::<BigEndian>().map_err(&t)?;
+		let hardware_revision = if hw_raw == 0xFFFFFFFF { None } else { Some(hw_raw) };
+
+		// Four bytes of valid and enabled flags, of which only the last two are used, and one reserved byte
+		let flags:u32 = rdr.read_u32::<BigEndian>().map_err(&t)?;
+		let flags1:u8 = (flags >> 8) as u8;
+		let flags2:u8 = flags as u8;
+		let _reserved:u8 = rdr.read_u8().map_err(&t)?;
+
+		let radio_count:u8           = rdr.read_u8().map_err(&t)?;
+		let satellites_locked:u8     = rdr.read_u8().map_err(&t)?;
+		let satellites_tracked:u8    = rdr.read_u8().map_err(&t)?;
+		let uat_targets:u16          = rdr.read_u16::<BigEndian>().map_err(&t)?;
+		let es_targets:u16           = rdr.read_u16::<BigEndian>().map_err(&t)?;
+		let uat_messages_per_min:u16 = rdr.read_u16::<BigEndian>().map_err(&t)?;
+		let es_messages_per_min:u16  = rdr.read_u16::<BigEndian>().map_err(&t)?;
+		let cpu_temp_raw:i16         = rdr.read_i16::<BigEndian>().map_err(&t)?;
+		let tower_count:u8           = rdr.read_u8().map_err(&t)?;
+
+		let towers_len:usize = STATUS_LENGTH + 6*(tower_count as usize);
+		require(0x53, towers_len, data)?;
+		let lat_lon = |b:&[u8]| ((((b[0] as i32) << 24) | ((b[1] as i32) << 16) | ((b[2] as i32) << 8)) >> 8) as f32 * LAT_LON_SCALE;
+		let towers:Vec<(f32, f32)> = data[STATUS_LENGTH..towers_len].chunks(6)
+			.map(|tower| (lat_lon(&tower[..3]), lat_lon(&tower[3..])))
+			.collect();
+
+		Ok(Status {
+			protocol_version, firmware_version, hardware_revision,
+			gps_solution:        flags2 & 0x03,
+			ahrs_valid:          flags2 & 0x04 != 0,
+			pres_altitude_valid: flags2 & 0x08 != 0,
+			uat_enabled:         flags2 & 0x20 != 0,
+			es_enabled:          flags2 & 0x40 != 0,
+			gps_enabled:         flags2 & 0x80 != 0,
+			ahrs_enabled:        flags1 & 0x01 != 0,
+			radio_count, satellites_locked, satellites_tracked, uat_targets, es_targets, uat_messages_per_min,
+			es_messages_per_min,
+			cpu_temp_c: if flags2 & 0x10 != 0 { Some(cpu_temp_raw as f32 * 0.1) } else { None },
+			towers,
+		})
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let flag = |b:bool, mask:u8| if b { mask } else { 0 };
+		let flags1:u8 = flag(self.ahrs_enabled, 0x01);
+		let flags2:u8 = (self.gps_solution & 0x03) | flag(self.ahrs_valid, 0x04) | flag(self.pres_altitude_valid, 0x08) |
+			flag(self.cpu_temp_c.is_some(), 0x10) | flag(self.uat_enabled, 0x20) | flag(self.es_enabled, 0x40) |
+			flag(self.gps_enabled, 0x80);
+
+		let mut ans:Vec<u8> = vec![self.protocol_version];
+		ans.extend_from_slice(&self.firmware_version);
+		ans.extend_from_slice(&self.hardware_revision.unwrap_or(0xFFFFFFFF).to_be_bytes());
+		ans.extend_from_slice(&[0x00, 0x00, flags1, flags2, 0x00, self.radio_count, self.satellites_locked, self.satellites_tracked]);
+		for x in &[self.uat_targets, self.es_targets, self.uat_messages_per_min, self.es_messages_per_min] {
+			ans.extend_from_slice(&x.to_be_bytes());
+		}
+		ans.extend_from_slice(&((self.cpu_temp_c.unwrap_or(0.0) * 10.0).round() as i16).to_be_bytes());
+
+		ans.push(self.towers.len().min(255) as u8);
+		for (lat, lon) in self.towers.iter().take(255) {
+			ans.extend_from_slice(&((lat / LAT_LON_SCALE).round() as i32).to_be_bytes()[1..]);
+			ans.extend_from_slice(&((lon / LAT_LON_SCALE).round() as i32).to_be_bytes()[1..]);
+		}
+		ans
+	}
+
+}
@@ -1,7 +1,26 @@
-use crate::StratusGDL90;
+use crate::{Gdl90Error, StratusGDL90};
+use crate::preprocessing::encode_frame as frame;
+
+// The traffic report from AAL2065 in traffic_report_from_udp_packet, with the message ID but without framing.  NIC 8
+// and NACp 9, no alert, an ICAO address, and an updated true track
+const SAMPLE_REPORT: [u8; 28] = [
+    0x14, 0x00, 0xA1, 0x09, 0x31, 0x17, 0x9C, 0xFB,
+    0xB9, 0xCF, 0x03, 0x46, 0xD9, 0x89, 0x1C, 0x70,
+    0x11, 0xCE, 0x03, 0x41, 0x41, 0x4C, 0x32, 0x30,
+    0x36, 0x35, 0x20, 0x00
+];
+
+// The sample report with some bytes replaced, given as (index, value) pairs
+fn sample_report_with(changes:&[(usize, u8)]) -> Vec<u8> {
+    let mut msg = SAMPLE_REPORT.to_vec();
+    for (i, b) in changes {
+        msg[*i] = *b;
+    }
+    msg
+}
 
 #[test]
-fn traffic_report_from_udp_packet() -> Result<(), &'static str> {
+fn traffic_report_from_udp_packet() -> Result<(), Gdl90Error> {
 
     const UDP_PACKET: [u8; 32] = [
         0x7E, 0x14, 0x00, 0xA1, 0x09, 0x31, 0x17, 0x9C,
@@ -11,12 +30,948 @@ fn traffic_report_from_udp_packet() -> Result<(), &'static str> {
     ];
 
     let report = StratusGDL90::from_udp_packet(&UDP_PACKET)?;
+    assert_eq!(report.encode_frame(), UDP_PACKET.to_vec());
+
     let report = report.into_traffic_report().unwrap();
 
     assert_eq!(report.callsign.as_str(), "AAL2065");
-    assert_eq!(report.latitude_deg, 33.20607);
-    assert_eq!(report.longitude_deg, -98.706604);
+    assert_eq!(report.latitude_deg, Some(33.20607));
+    assert_eq!(report.longitude_deg, Some(-98.706604));
+    assert_eq!(report.pres_altitude_ft, Some(27325.0));
+    assert_eq!(report.horz_velocity_kts, Some(455.0));
+    assert_eq!(report.vert_velocity_fpm, Some(1088.0));
     assert_eq!(report.participant_address, 0xA10931);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn traffic_report_with_escaped_bytes() -> Result<(), Gdl90Error> {
+
+    // The sample report with 0x7E and 0x7D in the latitude and longitude
+    let msg = sample_report_with(&[(6, 0x7E), (9, 0x7D)]);
+
+    let packet = frame(&msg);
+    assert!(packet.len() > msg.len() + 4);
+
+    let report = StratusGDL90::from_udp_packet(&packet)?;
+    let report = report.into_traffic_report().unwrap();
+
+    assert_eq!(report.callsign.as_str(), "AAL2065");
+    assert_eq!(report.latitude_deg, Some((0x177EFB as f32) * (180.0 / 8388608.0)));
+    assert_eq!(report.longitude_deg, Some(((0xB97D03 - 0x1000000) as f32) * (180.0 / 8388608.0)));
+
+    Ok(())
+}
+
+#[test]
+fn udp_packet_with_bad_fcs_is_rejected() {
+    let mut packet = frame(&[0x0B, 0x01, 0x2C, 0x00, 0x0A]);
+    let n = packet.len();
+    packet[n-2] ^= 0x01;
+
+    match StratusGDL90::from_udp_packet(&packet) {
+        Err(Gdl90Error::CrcMismatch{ expected, actual }) => assert_eq!(expected ^ actual, 0x0100),
+        other => panic!("Expected an FCS mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn truncated_messages_report_their_length() {
+    assert_eq!(StratusGDL90::from_udp_packet(&frame(&[0x0B, 0x01, 0x2C])).unwrap_err(),
+        Gdl90Error::Truncated{ msg_id: 11, needed: 4, got: 2 });
+    assert_eq!(StratusGDL90::from_udp_packet(&frame(&[0x14, 0x00, 0xA1, 0x09, 0x31])).unwrap_err(),
+        Gdl90Error::Truncated{ msg_id: 20, needed: 27, got: 4 });
+    assert_eq!(StratusGDL90::from_udp_packet(&[0x14, 0x00]).unwrap_err(), Gdl90Error::BadFlag(0x14));
+    assert_eq!(StratusGDL90::from_udp_packet(&frame(&[0x65, 0x09])).unwrap_err(),
+        Gdl90Error::UnknownSubId{ msg_id: 101, sub_id: 9 });
+}
+
+#[test]
+fn multiple_frames_in_one_datagram() {
+    let mut datagram = frame(&[0x0B, 0x01, 0x2C, 0x00, 0x0A]);
+    let mut corrupted = frame(&[0x0B, 0x00, 0x10, 0x00, 0x0A]);
+    corrupted[2] ^= 0x80;
+    datagram.extend(corrupted);
+    datagram.extend(frame(&[0xF0, 0x01, 0x02]));
+
+    let results = StratusGDL90::from_udp_datagram(&datagram);
+    assert_eq!(results.len(), 3);
+    assert!(matches!(results[0], Ok(StratusGDL90::OwnshipGeometricAltitude{ geo_altitude_ft, vertical_warning: false, vfom_m: Some(10) }) if geo_altitude_ft == 1500.0));
+    assert!(results[1].is_err());
+    assert!(matches!(results[2], Ok(StratusGDL90::Unknown{ id: 0xF0, .. })));
+
+    // A last frame without its closing flag is reported rather than dropped
+    let mut cut_off = frame(&[0x0B, 0x01, 0x2C, 0x00, 0x0A]);
+    cut_off.pop();
+    datagram.extend(cut_off);
+    let results = StratusGDL90::from_udp_datagram(&datagram);
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[3].as_ref().unwrap_err(), &Gdl90Error::Unterminated);
+}
+
+#[test]
+fn encoded_messages_round_trip() -> Result<(), Gdl90Error> {
+    let messages = vec![
+        StratusGDL90::Initialization{ audio_test: false, audio_inhibit: true, cdti_ok: true, csa_audio_disable: true, csa_disable: false },
+        StratusGDL90::HeightAboveTerrain{ hat_ft: Some(-25) },
+        StratusGDL90::HeightAboveTerrain{ hat_ft: None },
+        StratusGDL90::OwnshipGeometricAltitude{ geo_altitude_ft: -300.0, vertical_warning: true, vfom_m: Some(10) },
+        StratusGDL90::OwnshipGeometricAltitude{ geo_altitude_ft: 12500.0, vertical_warning: false, vfom_m: None },
+        StratusGDL90::Attitude{ roll_deg: Some(-12.5), pitch_deg: None, hdg_deg: None, hdg_is_true: false, ias_kts: Some(110), tas_kts: None },
+        StratusGDL90::Attitude{ roll_deg: None, pitch_deg: Some(3.5), hdg_deg: Some(271.5), hdg_is_true: true, ias_kts: None, tas_kts: Some(121) },
+        StratusGDL90::Unknown{ id: 0xF0, data: vec![0x7E, 0x7D, 0x00] },
+    ];
+
+    for msg in messages {
+        let packet = msg.encode_frame();
+        let decoded = StratusGDL90::from_udp_packet(&packet)?;
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", msg));
+        assert_eq!(decoded.encode_frame(), packet);
+    }
+
+    let mut report = crate::traffic_report::TrafficReport::new();
+    report.nav_integrity_category = 8;
+    report.latitude_deg = Some(-45.0);
+    report.longitude_deg = Some(-123.75);
+    report.pres_altitude_ft = Some(4500.0);
+    report.vert_velocity_fpm = Some(-640.0);
+    report.track_heading_deg = 357.1875;
+    report.callsign = String::from("N12345");
+
+    let decoded = StratusGDL90::from_udp_packet(&StratusGDL90::TrafficReport(report).encode_frame())?;
+    let decoded = decoded.into_traffic_report().unwrap();
+    assert_eq!(decoded.latitude_deg, Some(-45.0));
+    assert_eq!(decoded.longitude_deg, Some(-123.75));
+    assert_eq!(decoded.pres_altitude_ft, Some(4500.0));
+    assert_eq!(decoded.horz_velocity_kts, None);
+    assert_eq!(decoded.vert_velocity_fpm, Some(-640.0));
+    assert_eq!(decoded.track_heading_deg, 357.1875);
+    assert_eq!(decoded.callsign.as_str(), "N12345");
+
+    // Without a position the NIC is sent as zero, so a receiver doesn't take the zeroed coordinates as real
+    let mut report = decoded;
+    report.longitude_deg = None;
+    let bytes = report.to_bytes();
+    assert_eq!(bytes[12] >> 4, 0);
+    let decoded = crate::traffic_report::TrafficReport::from_slice(&bytes)?;
+    assert_eq!(decoded.latitude_deg, None);
+    assert_eq!(decoded.longitude_deg, None);
+
+    Ok(())
+}
+
+#[test]
+fn heartbeat_status_and_counts() -> Result<(), Gdl90Error> {
+    // GPS position valid, UAT initialized, timestamp bit 16 and UTC OK, 0x1C7E7 seconds, 2 uplinks and 18 basic/long
+    let packet = frame(&[0x00, 0x81, 0x81, 0xE7, 0xC7, 0x10, 0x12]);
+
+    match StratusGDL90::from_udp_packet(&packet)? {
+        StratusGDL90::Heartbeat(h) => {
+            assert!(h.gps_position_valid && h.uat_initialized && h.utc_ok);
+            assert!(!h.maintenance_required && !h.gps_battery_low && !h.csa_requested);
+            assert_eq!(h.timestamp_s, 0x1C7E7);
+            assert_eq!(h.uplink_msg_count, 2);
+            assert_eq!(h.basic_long_msg_count, 18);
+            assert_eq!(StratusGDL90::Heartbeat(h).encode_frame(), packet);
+        },
+        other => panic!("Expected a heartbeat, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn ownship_geometric_altitude_metrics() -> Result<(), Gdl90Error> {
+    let decode = |bytes:[u8; 5]| -> Result<(f32, bool, Option<u16>), Gdl90Error> {
+        match StratusGDL90::from_udp_packet(&frame(&bytes))? {
+            StratusGDL90::OwnshipGeometricAltitude{ geo_altitude_ft, vertical_warning, vfom_m } => Ok((geo_altitude_ft, vertical_warning, vfom_m)),
+            other => panic!("Expected ownship geometric altitude, got {:?}", other),
+        }
+    };
+
+    // 0x7FFF means the VFOM isn't available
+    assert_eq!(decode([0x0B, 0x09, 0xC4, 0x7F, 0xFF])?, (12500.0, false, None));
+    // 0x7FFE means 32766 m or more
+    assert_eq!(decode([0x0B, 0xFF, 0xC4, 0x7F, 0xFE])?, (-300.0, false, Some(32766)));
+    // The vertical warning bit is separate from the VFOM
+    assert_eq!(decode([0x0B, 0x00, 0x00, 0x80, 0x32])?, (0.0, true, Some(50)));
+    assert_eq!(decode([0x0B, 0x00, 0x00, 0xFF, 0xFF])?, (0.0, true, None));
+
+    Ok(())
+}
+
+#[test]
+fn initialization_flags() -> Result<(), Gdl90Error> {
+    // Audio test 0x40, audio inhibit 0x02, and CDTI OK 0x01 in the first byte; CSA audio disable 0x02 and CSA disable
+    // 0x01 in the second
+    let flags = |byte1:u8, byte2:u8| -> Result<[bool; 5], Gdl90Error> {
+        match StratusGDL90::from_udp_packet(&frame(&[0x02, byte1, byte2]))? {
+            StratusGDL90::Initialization{ audio_test, audio_inhibit, cdti_ok, csa_audio_disable, csa_disable } =>
+                Ok([audio_test, audio_inhibit, cdti_ok, csa_audio_disable, csa_disable]),
+            other => panic!("Expected an initialization message, got {:?}", other),
+        }
+    };
+
+    assert_eq!(flags(0x40, 0x00)?, [true, false, false, false, false]);
+    assert_eq!(flags(0x02, 0x00)?, [false, true, false, false, false]);
+    assert_eq!(flags(0x01, 0x00)?, [false, false, true, false, false]);
+    assert_eq!(flags(0x00, 0x02)?, [false, false, false, true, false]);
+    assert_eq!(flags(0x00, 0x01)?, [false, false, false, false, true]);
+    // Spare bits are ignored
+    assert_eq!(flags(0xBC, 0xFC)?, [false; 5]);
+
+    let msg = StratusGDL90::Initialization{ audio_test: true, audio_inhibit: true, cdti_ok: true, csa_audio_disable: true, csa_disable: true };
+    assert_eq!(msg.to_bytes(), vec![0x02, 0x43, 0x03]);
+
+    Ok(())
+}
+
+#[test]
+fn height_above_terrain() -> Result<(), Gdl90Error> {
+    assert!(matches!(StratusGDL90::from_udp_packet(&frame(&[0x09, 0x04, 0xD2]))?, StratusGDL90::HeightAboveTerrain{ hat_ft: Some(1234) }));
+    assert!(matches!(StratusGDL90::from_udp_packet(&frame(&[0x09, 0x80, 0x00]))?, StratusGDL90::HeightAboveTerrain{ hat_ft: None }));
+    Ok(())
+}
+
+#[test]
+fn long_uat_report() -> Result<(), Gdl90Error> {
+    use crate::uat_report::{AddressQualifier, AirGroundState, AltitudeType};
+
+    const MESSAGE: [u8; 38] = [
+        0x1F, 0x10, 0x00, 0x00,
+        0x08, 0xA1, 0x23, 0x45, 0x35, 0x55, 0x57, 0x52, 0x22, 0x22, 0x0F, 0x18,
+        0x01, 0x96, 0x19, 0x80, 0xB8, 0x09, 0xD9, 0x0D, 0x02, 0x4A, 0x84, 0x0B,
+        0x00, 0x93, 0xCA, 0x00, 0x00, 0x0F, 0x50, 0x00, 0x00, 0x00
+    ];
+
+    let packet = frame(&MESSAGE);
+    let msg = StratusGDL90::from_udp_packet(&packet)?;
+    assert_eq!(msg.encode_frame(), packet);
+
+    match msg {
+        StratusGDL90::LongReport{ time_of_reception_ns, report } => {
+            assert_eq!(time_of_reception_ns, 1280);
+            assert_eq!(report.payload_type(), 1);
+            assert_eq!(report.address_qualifier(), AddressQualifier::AdsbIcao);
+            assert_eq!(report.address(), 0xA12345);
+            assert_eq!(report.raw(), &MESSAGE[4..]);
+
+            let sv = report.state_vector();
+            assert!((sv.latitude_deg.unwrap() - 37.5).abs() < 1e-4);
+            assert!((sv.longitude_deg.unwrap() + 122.25).abs() < 1e-4);
+            assert_eq!(sv.altitude_type, AltitudeType::Barometric);
+            assert_eq!(sv.altitude_ft, Some(5000.0));
+            assert_eq!(sv.nav_integrity_category, 8);
+            assert_eq!(sv.air_ground_state, AirGroundState::AirborneSubsonic);
+            assert_eq!(sv.north_velocity_kts, Some(100.0));
+            assert_eq!(sv.east_velocity_kts, Some(-50.0));
+            assert_eq!(sv.vert_velocity_fpm, Some(640.0));
+            assert!(sv.vert_velocity_is_geometric && sv.utc_coupled);
+
+            let ms = report.mode_status().unwrap();
+            assert_eq!(ms.callsign.as_str(), "N123AB");
+            assert!(ms.callsign_is_flight_id && ms.cdti_capable && ms.acas_installed && ms.atc_services);
+            assert_eq!(ms.uat_version, 2);
+            assert_eq!(ms.source_integrity_level, 3);
+            assert_eq!(ms.nav_accuracy_category_for_position, 9);
+            assert_eq!(ms.nav_accuracy_category_for_velocity, 1);
+
+            let aux = report.aux_state_vector().unwrap();
+            assert_eq!(aux.secondary_altitude_type, AltitudeType::Geometric);
+            assert_eq!(aux.secondary_altitude_ft, Some(5100.0));
+        },
+        other => panic!("Expected a long report, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn foreflight_device_id() -> Result<(), Gdl90Error> {
+    use crate::foreflight::{AltitudeDatum, InternetPolicy};
+
+    let mut msg = vec![0x65, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xE2, 0x40];
+    msg.extend_from_slice(b"Stratus\0");
+    msg.extend_from_slice(b"Stratus 3\0\0\0\0\0\0\0");
+    msg.extend_from_slice(&[0x00, 0x00, 0x00, 0x03]);
+
+    let packet = frame(&msg);
+    match StratusGDL90::from_udp_packet(&packet)? {
+        StratusGDL90::DeviceId(id) => {
+            assert_eq!(id.version, 1);
+            assert_eq!(id.serial_number, Some(123456));
+            assert_eq!(id.name.as_str(), "Stratus");
+            assert_eq!(id.long_name.as_str(), "Stratus 3");
+            assert_eq!(id.geometric_altitude_datum, AltitudeDatum::MeanSeaLevel);
+            assert_eq!(id.internet_policy, InternetPolicy::Expensive);
+            assert_eq!(StratusGDL90::DeviceId(id).encode_frame(), packet);
+        },
+        other => panic!("Expected a device ID, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn attitude_heading() -> Result<(), Gdl90Error> {
+    // Magnetic heading of -10.0 degrees, i.e. 350.0
+    let packet = frame(&[0x65, 0x01, 0x00, 0x32, 0xFF, 0xE2, 0xFF, 0x9C, 0x00, 0x64, 0x00, 0x6E]);
+
+    match StratusGDL90::from_udp_packet(&packet)? {
+        StratusGDL90::Attitude{ roll_deg, pitch_deg, hdg_deg, hdg_is_true, ias_kts, tas_kts } => {
+            assert_eq!(roll_deg, Some(5.0));
+            assert_eq!(pitch_deg, Some(-3.0));
+            assert_eq!(hdg_deg, Some(350.0));
+            assert!(!hdg_is_true);
+            assert_eq!(ias_kts, Some(100));
+            assert_eq!(tas_kts, Some(110));
+        },
+        other => panic!("Expected attitude, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn stratux_extensions() -> Result<(), Gdl90Error> {
+    // Roll 10.5, pitch -2.0, heading 90.0, slip/skid 1.2, yaw rate invalid, 1.1 G, 95 kts, 3500 ft, -300 fpm
+    let packet = frame(&[0x4C, 0x45, 0x01, 0x01, 0x00, 0x69, 0xFF, 0xEC, 0x03, 0x84, 0x00, 0x0C, 0x7F, 0xFF,
+        0x00, 0x0B, 0x00, 0x5F, 0x21, 0x34, 0xFE, 0xD4]);
+
+    match StratusGDL90::from_udp_packet(&packet)? {
+        StratusGDL90::StratuxAhrs(ahrs) => {
+            assert_eq!(ahrs.roll_deg, Some(10.5));
+            assert_eq!(ahrs.pitch_deg, Some(-2.0));
+            assert_eq!(ahrs.hdg_deg, Some(90.0));
+            assert_eq!(ahrs.slip_skid_deg, Some(1.2));
+            assert_eq!(ahrs.yaw_rate_dps, None);
+            assert_eq!(ahrs.g_load, Some(1.1));
+            assert_eq!(ahrs.ias_kts, Some(95));
+            assert_eq!(ahrs.pres_altitude_ft, Some(3500));
+            assert_eq!(ahrs.vert_velocity_fpm, Some(-300));
+            assert_eq!(StratusGDL90::StratuxAhrs(ahrs).encode_frame(), packet);
+        },
+        other => panic!("Expected Stratux AHRS, got {:?}", other),
+    }
+
+    assert!(matches!(StratusGDL90::from_udp_packet(&frame(&[0xCC, 0x07]))?,
+        StratusGDL90::StratuxHeartbeat{ gps_position_valid: true, ahrs_valid: true, protocol_version: 1 }));
+
+    // Stratux 1.6r1 with a 3D fix, CPU temperature, UAT and 1090ES, and one tower
+    let mut msg = vec![0x53, 0x01, 0x01, 0x06, 0x00, 0x72, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x01, 0xF1, 0x00, 0x02,
+        0x09, 0x0C, 0x00, 0x03, 0x00, 0x11, 0x01, 0x2C, 0x03, 0xE8, 0x01, 0xC2, 0x01];
+    msg.extend_from_slice(&[0x1C, 0x00, 0x00, 0xA8, 0x00, 0x00]);
+
+    let packet = frame(&msg);
+    match StratusGDL90::from_udp_packet(&packet)? {
+        StratusGDL90::StratuxStatus(status) => {
+            assert_eq!(status.firmware_version, [1, 6, 0, b'r']);
+            assert_eq!(status.hardware_revision, None);
+            assert_eq!(status.gps_solution, 1);
+            assert!(status.gps_enabled && status.uat_enabled && status.es_enabled && status.ahrs_enabled);
+            assert!(!status.ahrs_valid);
+            assert_eq!(status.radio_count, 2);
+            assert_eq!((status.satellites_locked, status.satellites_tracked), (9, 12));
+            assert_eq!((status.uat_targets, status.es_targets), (3, 17));
+            assert_eq!((status.uat_messages_per_min, status.es_messages_per_min), (300, 1000));
+            assert_eq!(status.cpu_temp_c, Some(45.0));
+            assert_eq!(status.towers, vec![(39.375, -123.75)]);
+            assert_eq!(StratusGDL90::StratuxStatus(status).encode_frame(), packet);
+        },
+        other => panic!("Expected Stratux status, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn vendor_extension_registry() -> Result<(), Gdl90Error> {
+    use crate::extension::{Extension, Registry};
+
+    #[derive(Debug, PartialEq)]
+    struct CabinPressure { altitude_ft: u16 }
+
+    impl Extension for CabinPressure {
+        fn to_bytes(&self) -> Vec<u8> {
+            let mut ans = vec![0x01];
+            ans.extend_from_slice(&self.altitude_ft.to_be_bytes());
+            ans
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register(0x7A, Some(0x01), |data: &[u8]| {
+        if data.len() < 3 {
+            return Err(Gdl90Error::Truncated{ msg_id: 0x7A, needed: 3, got: data.len() });
+        }
+        Ok(CabinPressure{ altitude_ft: ((data[1] as u16) << 8) | (data[2] as u16) })
+    });
+
+    let packet = frame(&[0x7A, 0x01, 0x1F, 0x40]);
+    let msg = StratusGDL90::from_udp_packet_with(&packet, &registry)?;
+    assert_eq!(msg.extension::<CabinPressure>(), Some(&CabinPressure{ altitude_ft: 8000 }));
+    assert_eq!(msg.encode_frame(), packet);
+
+    // Other sub-IDs and unregistered decoders still fall back to the built-in decoders
+    assert!(matches!(StratusGDL90::from_udp_packet_with(&frame(&[0x7A, 0x02]), &registry)?, StratusGDL90::Unknown{ id: 0x7A, .. }));
+    assert!(matches!(StratusGDL90::from_udp_packet(&packet)?, StratusGDL90::Unknown{ id: 0x7A, .. }));
+    assert!(StratusGDL90::from_udp_packet_with(&frame(&[0x7A, 0x01]), &registry).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn traffic_report_invalid_fields() -> Result<(), Gdl90Error> {
+    // NIC of 0, pressure altitude 0xFFF, horizontal velocity 0xFFF, and vertical velocity 0x800
+    let msg = sample_report_with(&[(11, 0xFF), (12, 0xF9), (13, 0x09), (14, 0xFF), (15, 0xF8), (16, 0x00)]);
+
+    let report = StratusGDL90::from_udp_packet(&frame(&msg))?.into_traffic_report().unwrap();
+    assert_eq!(report.latitude_deg, None);
+    assert_eq!(report.longitude_deg, None);
+    assert_eq!(report.pres_altitude_ft, None);
+    assert_eq!(report.horz_velocity_kts, None);
+    assert_eq!(report.vert_velocity_fpm, None);
+    assert_eq!(report.distance_nm_to(&report), None);
+
+    let projected = report.project(10.0);
+    assert_eq!(projected.latitude_deg, None);
+    assert_eq!(projected.pres_altitude_ft, None);
+
+    Ok(())
+}
+
+#[test]
+fn traffic_report_status_and_misc_indicators() -> Result<(), Gdl90Error> {
+    use crate::traffic_report::{AddressType, TrackType};
+    use crate::uat_report::EmergencyStatus;
+
+    // Traffic alert for a TIS-B track file; on the ground, extrapolated, magnetic heading, medical emergency
+    let msg = sample_report_with(&[(1, 0x13), (12, 0xD6), (27, 0x20)]);
+
+    let decoded = StratusGDL90::from_udp_packet(&frame(&msg))?;
+    assert_eq!(decoded.to_bytes(), msg);
+
+    let report = decoded.into_traffic_report().unwrap();
+    assert!(report.traffic_alert);
+    assert_eq!(report.address_type, AddressType::TisbTrackFile);
+    assert!(!report.airborne);
+    assert!(report.report_extrapolated);
+    assert_eq!(report.track_type, TrackType::MagneticHeading);
+    assert_eq!(report.emergency_status, EmergencyStatus::Medical);
+
+    // The unchanged sample report has none of these
+    let report = StratusGDL90::from_udp_packet(&frame(&SAMPLE_REPORT))?.into_traffic_report().unwrap();
+    assert!(!report.traffic_alert);
+    assert_eq!(report.address_type, AddressType::AdsbIcao);
+    assert_eq!(report.emergency_status, EmergencyStatus::NoEmergency);
+
+    // Reserved address type, emitter category, NIC, NACp, and emergency code are written back unchanged
+    let msg = sample_report_with(&[(1, 0x07), (13, 0xCD), (18, 0x0D), (27, 0x90)]);
+    let decoded = StratusGDL90::from_udp_packet(&frame(&msg))?;
+    assert_eq!(decoded.to_bytes(), msg);
+    let report = decoded.into_traffic_report().unwrap();
+    assert_eq!(report.address_type, AddressType::Reserved(7));
+    assert_eq!(report.emergency_status, EmergencyStatus::Reserved(9));
+    assert_eq!(report.nacp().to_u8(), 13);
+
+    Ok(())
+}
+
+#[test]
+fn nic_and_nacp_bounds() -> Result<(), Gdl90Error> {
+    use crate::integrity::{Nacp, Nic};
+
+    // NIC 8 and NACp 9
+    let report = StratusGDL90::from_udp_packet(&frame(&SAMPLE_REPORT))?.into_traffic_report().unwrap();
+    assert_eq!(report.nic(), Nic::Rc0_1Nm);
+    assert_eq!(report.nacp(), Nacp::Epu30M);
+    assert_eq!(report.containment_radius_m(), Some(185.2));
+    assert_eq!(report.position_uncertainty_m(), Some(30.0));
+
+    assert_eq!(Nic::Rc25M.containment_radius_nm(), Some(25.0 / 1852.0));
+    assert_eq!(Nacp::Epu4Nm.epu_nm(), Some(4.0));
+    assert_eq!(Nic::Unknown.containment_radius_m(), None);
+    assert_eq!(Nacp::from_u8(14), Nacp::Reserved(14));
+    assert_eq!(Nacp::Reserved(14).epu_m(), None);
+    for x in 0..16 {
+        assert_eq!(Nic::from_u8(x).to_u8(), x);
+        assert_eq!(Nacp::from_u8(x).to_u8(), x);
+    }
+    // A quality threshold is a bound on the uncertainty, which reserved and unknown values never meet
+    let meets = |nacp:Nacp, max_epu_m:f32| nacp.epu_m().map(|epu_m| epu_m <= max_epu_m).unwrap_or(false);
+    assert!(meets(Nacp::Epu3M, 30.0));
+    assert!(!meets(Nacp::Epu10Nm, 30.0));
+    assert!(!meets(Nacp::from_u8(13), 30.0));
+    assert!(!meets(Nacp::Unknown, 30.0));
+
+    Ok(())
+}
+
+#[test]
+fn traffic_table_merges_and_ages_out_targets() {
+    use std::time::{Duration, SystemTime};
+    use crate::traffic_report::{AddressType, TrackType, TrafficReport};
+    use crate::traffic_table::TrafficTable;
+
+    let t0 = SystemTime::now();
+    let report = |address:u32, address_type:AddressType, callsign:&str, lat:f32, secs:u64| {
+        let mut report = TrafficReport::new();
+        report.nav_integrity_category = 8;
+        report.participant_address = address;
+        report.address_type = address_type;
+        report.callsign = String::from(callsign);
+        report.latitude_deg = Some(lat);
+        report.longitude_deg = Some(-98.0);
+        report.horz_velocity_kts = Some(360.0);
+        report.recv_time = t0 + Duration::from_secs(secs);
+        report
+    };
+
+    let mut table = TrafficTable::new(2, Duration::from_secs(20));
+    table.update(report(0xA10931, AddressType::AdsbIcao, "AAL2065", 33.0, 0));
+    table.update(report(0xA10931, AddressType::TisbIcao, "", 33.5, 0));
+    table.update(report(0xA10931, AddressType::AdsbIcao, "", 33.1, 1));
+    let target = table.update(report(0xA10931, AddressType::AdsbIcao, "", 33.2, 2));
+
+    // The callsign is remembered and only the last two positions are kept
+    assert_eq!(target.report.callsign.as_str(), "AAL2065");
+    assert_eq!(target.report_count, 3);
+    assert_eq!(target.history.iter().map(|p| p.latitude_deg).collect::<Vec<f32>>(), vec![33.1, 33.2]);
+    assert_eq!(table.len(), 2);
+    assert_eq!(table.get(0xA10931, AddressType::TisbIcao).unwrap().report.callsign.as_str(), "");
+
+    // One minute at 360 knots due north is 6 NM, or a tenth of a degree of latitude
+    assert!(table.tick(t0 + Duration::from_secs(12)).is_empty());
+    let target = table.get(0xA10931, AddressType::AdsbIcao).unwrap();
+    assert!(target.extrapolated);
+    assert!(target.report.report_extrapolated);
+    assert!((target.report.latitude_deg.unwrap() - 33.2 - 0.1/6.0).abs() < 1e-3);
+    assert_eq!(target.last_report.latitude_deg, Some(33.2));
+
+    // The TIS-B target was last heard at t0, so it times out first
+    assert_eq!(table.tick(t0 + Duration::from_secs(21)), vec![(0xA10931, AddressType::TisbIcao)]);
+    assert_eq!(table.len(), 1);
+    table.tick(t0 + Duration::from_secs(23));
+    assert!(table.is_empty());
+
+    // A target without a valid track isn't moved, even though it has a speed
+    let mut no_track = report(0xA10931, AddressType::AdsbIcao, "", 33.2, 30);
+    no_track.track_type = TrackType::NotValid;
+    table.update(no_track);
+    table.tick(t0 + Duration::from_secs(40));
+    let target = table.get(0xA10931, AddressType::AdsbIcao).unwrap();
+    assert!(target.extrapolated);
+    assert_eq!(target.report.latitude_deg, Some(33.2));
+    assert_eq!(target.report.longitude_deg, Some(-98.0));
+}
+
+#[test]
+fn traffic_alerts_from_closest_point_of_approach() {
+    use crate::alerting::{assess, update_traffic_alert, ThreatLevel, Thresholds};
+    use crate::traffic_report::{TrackType, TrafficReport};
+
+    let aircraft = |nm_north:f32, alt_ft:f32, trk_deg:f32| {
+        let mut report = TrafficReport::new();
+        report.nav_integrity_category = 8;
+        let (lat, lon) = crate::util::destination_lat_lon(33.0, -98.0, nm_north.abs(), if nm_north < 0.0 { 180.0 } else { 0.0 });
+        report.latitude_deg = Some(lat);
+        report.longitude_deg = Some(lon);
+        report.pres_altitude_ft = Some(alt_ft);
+        report.horz_velocity_kts = Some(120.0);
+        report.vert_velocity_fpm = Some(0.0);
+        report.track_heading_deg = trk_deg;
+        report
+    };
+    let ownship = aircraft(0.0, 5000.0, 0.0);
+    let thresholds = Thresholds::default();
+
+    // Head on at 240 knots from 2.5 NM: modified tau is 34 seconds, inside the 40 second threshold
+    let mut target = aircraft(2.5, 5300.0, 180.0);
+    let encounter = update_traffic_alert(&ownship, &mut target, &thresholds).unwrap();
+    assert_eq!(encounter.threat, ThreatLevel::TrafficAdvisory);
+    assert!(target.traffic_alert);
+    assert!((encounter.range_nm - 2.5).abs() < 1e-3);
+    assert!((encounter.range_rate_kts + 240.0).abs() < 1e-2);
+    assert!((encounter.time_to_cpa_s - 37.5).abs() < 0.1);
+    assert!(encounter.horz_miss_nm < 1e-3);
+    assert_eq!(encounter.vert_miss_ft, Some(300.0));
+
+    // From 3 NM, modified tau is 42 seconds, so it's only proximate
+    let encounter = assess(&ownship, &aircraft(3.0, 5300.0, 180.0), &thresholds).unwrap();
+    assert_eq!(encounter.threat, ThreatLevel::Proximate);
+
+    // Outside ZTHR with no vertical closure
+    let encounter = assess(&ownship, &aircraft(2.5, 6000.0, 180.0), &thresholds).unwrap();
+    assert_eq!(encounter.threat, ThreatLevel::Proximate);
+
+    // A target 1000 ft above and descending at 2000 fpm reaches ownship's altitude in 30 seconds
+    let mut descending = aircraft(2.5, 6000.0, 180.0);
+    descending.vert_velocity_fpm = Some(-2000.0);
+    assert_eq!(assess(&ownship, &descending, &thresholds).unwrap().threat, ThreatLevel::TrafficAdvisory);
+
+    // Moving away
+    let encounter = assess(&ownship, &aircraft(-8.0, 5000.0, 180.0), &thresholds).unwrap();
+    assert_eq!(encounter.threat, ThreatLevel::Other);
+    assert_eq!(encounter.time_to_cpa_s, 0.0);
+    assert!((encounter.horz_miss_nm - 8.0).abs() < 1e-3);
+
+    // A speed with no valid track is ignored, so the head-on target above is only closing at ownship's speed and is
+    // proximate rather than an advisory
+    let mut no_track = aircraft(2.5, 5300.0, 180.0);
+    no_track.track_type = TrackType::NotValid;
+    let encounter = assess(&ownship, &no_track, &thresholds).unwrap();
+    assert_eq!(encounter.threat, ThreatLevel::Proximate);
+    assert!((encounter.range_rate_kts + 120.0).abs() < 1e-2);
+
+    // No position
+    let mut target = TrafficReport::new();
+    target.traffic_alert = true;
+    assert_eq!(update_traffic_alert(&ownship, &mut target, &thresholds), None);
+    assert!(!target.traffic_alert);
+}
+
+#[test]
+fn relative_position_helpers() {
+    use crate::traffic_report::{TrackType, TrafficReport, VerticalTrend};
+
+    let mut ownship = TrafficReport::new();
+    ownship.nav_integrity_category = 8;
+    ownship.latitude_deg = Some(33.0);
+    ownship.longitude_deg = Some(-98.0);
+    ownship.pres_altitude_ft = Some(5000.0);
+    ownship.track_heading_deg = 310.0;
+
+    // 3 NM northeast of ownship, 520 ft above and descending
+    let mut target = TrafficReport::new();
+    let (lat, lon) = ownship.lat_lon_at(3.0, 45.0).unwrap();
+    target.latitude_deg = Some(lat);
+    target.longitude_deg = Some(lon);
+    target.pres_altitude_ft = Some(5520.0);
+    target.vert_velocity_fpm = Some(-700.0);
+
+    assert!((ownship.distance_nm_to(&target).unwrap() - 3.0).abs() < 0.1);
+    assert!((ownship.bearing_deg_to(&target).unwrap() - 45.0).abs() < 0.1);
+    assert!((ownship.relative_bearing_deg_to(&target, None).unwrap() - 95.0).abs() < 0.1);
+    assert_eq!(ownship.clock_position_of(&target, None), Some(3));
+
+    // Without a valid track there's nothing to measure from, but the true bearing is still known
+    let mut no_track = ownship.clone();
+    no_track.track_type = TrackType::NotValid;
+    assert_eq!(no_track.relative_bearing_deg_to(&target, None), None);
+    assert_eq!(no_track.clock_position_of(&target, None), None);
+
+    // A magnetic heading of 295 with 15 degrees of east variation is 310 true.  Without the variation the clock
+    // position could be an hour out, so there isn't one
+    let mut magnetic = ownship.clone();
+    magnetic.track_type = TrackType::MagneticHeading;
+    magnetic.track_heading_deg = 295.0;
+    assert_eq!(magnetic.relative_bearing_deg_to(&target, None), None);
+    assert_eq!(magnetic.clock_position_of(&target, None), None);
+    assert!((magnetic.relative_bearing_deg_to(&target, Some(15.0)).unwrap() - 95.0).abs() < 0.1);
+    assert_eq!(magnetic.clock_position_of(&target, Some(15.0)), Some(3));
+    // The variation only applies to magnetic headings
+    assert_eq!(ownship.clock_position_of(&target, Some(15.0)), Some(3));
+    assert!(no_track.bearing_deg_to(&target).is_some());
+
+    let rel_alt = ownship.relative_altitude_of(&target).unwrap();
+    assert_eq!(rel_alt.hundreds_ft, 5);
+    assert_eq!(rel_alt.trend, VerticalTrend::Descending);
+    assert_eq!(rel_alt.to_string(), "+05\u{2193}");
+    assert_eq!(target.relative_altitude_of(&ownship).unwrap().to_string(), "-05");
+
+    assert_eq!(crate::util::clock_position(350.0), 12);
+    assert_eq!(crate::util::clock_position(-60.0), 10);
+    assert_eq!(target.clock_position_of(&TrafficReport::new(), None), None);
+
+    // Projecting due east follows the great circle instead of drifting north or south
+    ownship.horz_velocity_kts = Some(600.0);
+    ownship.track_heading_deg = 90.0;
+    let projected = ownship.project(60.0);
+    assert!((ownship.distance_nm_to(&projected).unwrap() - 10.0).abs() < 0.05);
+    assert!((ownship.bearing_deg_to(&projected).unwrap() - 90.0).abs() < 0.1);
+}
+
+#[test]
+fn wgs84_geodesy() {
+    use crate::geodesy::{self, EarthModel, Ecef, Enu};
+
+    let dms = |d:f64, m:f64, s:f64| d.signum() * (d.abs() + m/60.0 + s/3600.0);
+
+    // Flinders Peak to Buninyong, the example from Vincenty's paper
+    let (lat1, lon1) = (dms(-37.0, 57.0, 3.72030), dms(144.0, 25.0, 29.52440));
+    let (lat2, lon2) = (dms(-37.0, 39.0, 10.15610), dms(143.0, 55.0, 35.38390));
+
+    let g = geodesy::inverse(EarthModel::Wgs84, lat1, lon1, lat2, lon2);
+    assert!((g.distance_m - 54972.271).abs() < 1e-3);
+    assert!((g.initial_bearing_deg - dms(306.0, 52.0, 5.37)).abs() < 1e-5);
+    assert!((g.final_bearing_deg - dms(307.0, 10.0, 25.07)).abs() < 1e-5);
+
+    let (lat, lon) = geodesy::direct(EarthModel::Wgs84, lat1, lon1, g.initial_bearing_deg, g.distance_m);
+    assert!((lat - lat2).abs() < 1e-9 && (lon - lon2).abs() < 1e-9);
+
+    // The sphere is close, but not that close
+    let sphere = geodesy::inverse(EarthModel::Sphere, lat1, lon1, lat2, lon2);
+    assert!((sphere.distance_m - geodesy::haversine_m(lat1, lon1, lat2, lon2)).abs() < 1e-9);
+    assert!((sphere.distance_m - g.distance_m).abs() > 10.0);
+    assert!((sphere.distance_m - g.distance_m).abs() < 300.0);
+
+    // Coincident and nearly antipodal points
+    assert_eq!(geodesy::inverse(EarthModel::Wgs84, lat1, lon1, lat1, lon1).distance_m, 0.0);
+    assert!(geodesy::inverse(EarthModel::Wgs84, 0.0, 0.0, 0.5, 179.7).distance_m > 1.99e7);
+
+    let ecef = Ecef::from_geodetic(0.0, 0.0, 0.0);
+    assert!((ecef.x_m - geodesy::WGS84_A).abs() < 1e-6 && ecef.y_m.abs() < 1e-6 && ecef.z_m.abs() < 1e-6);
+    let ecef = Ecef::from_geodetic(90.0, 0.0, 100.0);
+    assert!((ecef.z_m - geodesy::WGS84_B - 100.0).abs() < 1e-6);
+
+    let (lat, lon, h) = Ecef::from_geodetic(lat1, lon1, 10668.0).to_geodetic();
+    assert!((lat - lat1).abs() < 1e-9 && (lon - lon1).abs() < 1e-9 && (h - 10668.0).abs() < 1e-3);
+
+    // Buninyong is about 44 km west and 33 km north of Flinders Peak, and below its horizon
+    let enu = Enu::between(lat1, lon1, 0.0, lat2, lon2, 0.0);
+    assert!((enu.east_m.hypot(enu.north_m) - g.distance_m).abs() < 5.0);
+    assert!(enu.east_m < -43000.0 && enu.north_m > 32000.0 && enu.up_m < -200.0);
+    let (lat, lon, h) = enu.to_ecef(lat1, lon1, 0.0).to_geodetic();
+    assert!((lat - lat2).abs() < 1e-9 && (lon - lon2).abs() < 1e-9 && h.abs() < 1e-3);
+}
+
+#[test]
+fn ownship_state_from_messages() -> Result<(), Gdl90Error> {
+    use std::time::{Duration, SystemTime};
+    use crate::foreflight::AltitudeDatum;
+    use crate::ownship::OwnshipState;
+
+    let t0 = SystemTime::now();
+    let mut ownship = OwnshipState::new();
+    assert_eq!(ownship.position(), None);
+
+    // Ownship report, the same format as a traffic report
+    let ownship_report = sample_report_with(&[(0, 0x0A)]);
+    assert!(ownship.update_at(&StratusGDL90::from_udp_packet(&frame(&ownship_report))?, t0));
+    assert!(ownship.update_at(&StratusGDL90::from_udp_packet(&frame(&[0x00, 0x81, 0x00, 0x00, 0x00, 0x00, 0x00]))?, t0));
+    assert!(ownship.update_at(&StratusGDL90::OwnshipGeometricAltitude{ geo_altitude_ft: 27625.0, vertical_warning: false, vfom_m: Some(10) }, t0 + Duration::from_secs(1)));
+    assert!(ownship.update_at(&StratusGDL90::HeightAboveTerrain{ hat_ft: None }, t0));
+    assert!(ownship.update_at(&StratusGDL90::Attitude{ roll_deg: Some(5.0), pitch_deg: Some(1.5), hdg_deg: Some(90.0), hdg_is_true: true, ias_kts: Some(250), tas_kts: None }, t0));
+    assert!(!ownship.update_at(&StratusGDL90::Unknown{ id: 0xF0, data: vec![] }, t0));
+
+    assert_eq!(ownship.position(), Some((33.20607, -98.706604)));
+    assert_eq!(ownship.pres_altitude_ft(), Some(27325.0));
+    assert_eq!(ownship.altitude_difference_ft(), Some(300.0));
+    // Standard GDL90 geometric altitude is above the WGS-84 ellipsoid, which can't give an altimeter setting
+    assert_eq!(ownship.derived_altimeter_setting_inhg(), None);
+    ownship.geo_altitude_datum = Some(AltitudeDatum::Wgs84Ellipsoid);
+    assert_eq!(ownship.derived_altimeter_setting_inhg(), None);
+    ownship.geo_altitude_datum = Some(AltitudeDatum::MeanSeaLevel);
+    assert!((ownship.derived_altimeter_setting_inhg().unwrap() - 30.22).abs() < 1e-4);
+    assert_eq!(ownship.gps_position_valid(), Some(true));
+    assert_eq!(ownship.hat_ft.as_ref().unwrap().value, None);
+    assert_eq!(ownship.attitude().unwrap().ias_kts, Some(250.0));
+
+    // Each part has its own timestamp
+    let now = t0 + Duration::from_secs(3);
+    assert!(ownship.report.as_ref().unwrap().is_stale(Duration::from_millis(2500), now));
+    assert!(!ownship.geo_altitude.as_ref().unwrap().is_stale(Duration::from_millis(2500), now));
+
+    // The Stratux heartbeat also updates GPS validity
+    ownship.update_at(&StratusGDL90::StratuxHeartbeat{ gps_position_valid: false, ahrs_valid: true, protocol_version: 1 }, now);
+    assert_eq!(ownship.gps_position_valid(), Some(false));
+    assert_eq!(ownship.gps_position_valid.as_ref().unwrap().age(now), Duration::from_secs(0));
+
+    Ok(())
+}
+
+#[test]
+fn uplink_apdu_headers() -> Result<(), Gdl90Error> {
+    use crate::uplink_data::{Frame, ProductTime, Segment};
+
+    let mut msg:Vec<u8> = vec![0x07, 0x00, 0x00, 0x00];
+    // Ground station at 0, 0
+    msg.extend_from_slice(&[0x00; 8]);
+    // Text product at 12:34:56
+    msg.extend_from_slice(&[0x02, 0x80, 0x06, 0x74, 0xB2, 0x2E, 0x00]);
+    // Segment 2 of 3 of product file 5, a NEXRAD CONUS product at 23:59:30 on October 17
+    msg.extend_from_slice(&[0x06, 0x00, 0x01, 0x03, 0xD4, 0x6F, 0xDB, 0xC0, 0x28, 0x0C, 0x04, 0x01, 0x02, 0x03]);
+    // Product 8 with the A flag set, at 06:07 on February 29
+    msg.extend_from_slice(&[0x03, 0x00, 0x80, 0x21, 0x17, 0x4C, 0x38, 0xAA]);
+
+    let payload = match StratusGDL90::from_udp_packet(&frame(&msg))? {
+        StratusGDL90::UplinkData{ payload, .. } => payload,
+        other => panic!("Expected uplink data, got {:?}", other),
+    };
+    assert_eq!(payload.application_data().len(), 3);
+
+    match &payload.application_data()[0] {
+        Frame::GenericText{ header, .. } => {
+            assert_eq!(header.product_id, 413);
+            assert_eq!(header.time, ProductTime{ month: None, day: None, hours: 12, minutes: 34, seconds: Some(56) });
+        },
+        other => panic!("Expected text, got {:?}", other),
+    }
+    match &payload.application_data()[1] {
+        Frame::Segmented{ header, payload } => {
+            assert_eq!(header.product_id, 64);
+            assert_eq!(header.time, ProductTime{ month: Some(10), day: Some(17), hours: 23, minutes: 59, seconds: Some(30) });
+            assert_eq!(header.segment, Some(Segment{ product_file_id: 5, product_file_length: 3, apdu_number: 2 }));
+            assert_eq!(payload, &vec![0x01, 0x02, 0x03]);
+        },
+        other => panic!("Expected a segment, got {:?}", other),
+    }
+    match &payload.application_data()[2] {
+        Frame::Unknown{ header, payload } => {
+            assert!(header.application_flag && !header.geo_flag && !header.provider_flag);
+            assert_eq!(header.product_id, 8);
+            assert_eq!(header.time, ProductTime{ month: Some(2), day: Some(29), hours: 6, minutes: 7, seconds: None });
+            assert_eq!(payload, &vec![0xAA]);
+        },
+        other => panic!("Expected an unknown product, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn segmented_products_are_reassembled() -> Result<(), Gdl90Error> {
+    use std::time::{Duration, SystemTime};
+    use crate::uplink_data::Frame;
+    use crate::uplink_data::reassembly::Reassembler;
+
+    // Segments 1 and 2 of 2 of product file 7 of product 8
+    let uplink = |segment:&[u8]| -> Result<crate::uplink_data::Payload, Gdl90Error> {
+        let msg:Vec<u8> = [&[0x07, 0x00, 0x00, 0x00][..], &[0x00; 8][..], segment].concat();
+        match StratusGDL90::from_udp_packet(&frame(&msg))? {
+            StratusGDL90::UplinkData{ payload, .. } => Ok(payload),
+            other => panic!("Expected uplink data, got {:?}", other),
+        }
+    };
+    let first  = uplink(&[0x04, 0x80, 0x00, 0x22, 0x04, 0x20, 0x1C, 0x04, 0x01, 0x11, 0x22])?;
+    let second = uplink(&[0x04, 0x00, 0x00, 0x22, 0x04, 0x20, 0x1C, 0x04, 0x02, 0x33])?;
+
+    let t0 = SystemTime::now();
+    let mut reassembler = Reassembler::new(Duration::from_secs(60));
+
+    // Out of order, with a repeat
+    assert!(reassembler.add_at(&second, t0).is_empty());
+    assert!(reassembler.add_at(&second, t0).is_empty());
+    assert_eq!(reassembler.len(), 1);
+    let products = reassembler.add_at(&first, t0 + Duration::from_secs(10));
+    assert_eq!(products.len(), 1);
+    assert!(reassembler.is_empty());
+    match &products[0].frame {
+        Frame::Unknown{ header, payload } => {
+            assert_eq!(header.product_id, 8);
+            assert_eq!(payload, &vec![0x11, 0x22, 0x33]);
+        },
+        other => panic!("Expected an unknown product, got {:?}", other),
+    }
+
+    // An incomplete product is dropped after the timeout, so a late segment doesn't complete it
+    assert!(reassembler.add_at(&first, t0).is_empty());
+    assert!(reassembler.add_at(&second, t0 + Duration::from_secs(61)).is_empty());
+    assert_eq!(reassembler.len(), 1);
+    assert_eq!(reassembler.expire(t0 + Duration::from_secs(200)), 1);
+
+    Ok(())
+}
+
+#[test]
+fn nexrad_blocks() -> Result<(), Gdl90Error> {
+    use crate::uplink_data::Frame;
+    use crate::uplink_data::nexrad::{decode_blocks, NexradProduct};
+
+    // A CONUS uplink with one run-length encoded block: 64 bins of 0, 32 of 3, 31 of 5, and 1 of 7
+    let msg:Vec<u8> = [&[0x07, 0x00, 0x00, 0x00][..], &[0x00; 8][..],
+        &[0x06, 0x00, 0x01, 0x00, 0x04, 0x20, 0x84, 0x1F, 0xF5, 0xF8, 0xF8, 0xFB, 0xF5, 0x07][..]].concat();
+    let payload = match StratusGDL90::from_udp_packet(&frame(&msg))? {
+        StratusGDL90::UplinkData{ payload, .. } => payload,
+        other => panic!("Expected uplink data, got {:?}", other),
+    };
+    let block = match &payload.application_data()[0] {
+        Frame::NexradPrecipitationImage{ header, product, blocks } => {
+            assert_eq!((header.time.hours, header.time.minutes), (1, 2));
+            assert_eq!(*product, NexradProduct::Conus);
+            assert_eq!(blocks.len(), 1);
+            blocks[0].clone()
+        },
+        other => panic!("Expected NEXRAD, got {:?}", other),
+    };
+
+    // Block 270325 is in ring 600 (40 degrees north) and 325 blocks east of the prime meridian (100 degrees west)
+    assert_eq!(block.block_number, 270325);
+    assert_eq!(block.scale, 1);
+    assert!((block.south_lat_deg() - 40.0).abs() < 1e-4);
+    assert!((block.north_lat_deg - (40.0 + 4.0/60.0)).abs() < 1e-4);
+    assert!((block.west_lon_deg + 100.0).abs() < 1e-3);
+    assert!((block.east_lon_deg() + 99.2).abs() < 1e-3);
+    assert_eq!((block.bin(1, 31), block.bin(2, 0), block.bin(3, 30), block.bin(3, 31)), (0, 3, 5, 7));
+
+    // Empty blocks 2 and 5 blocks east of the reference block, which is also empty
+    let blocks = decode_blocks(&[0x04, 0x1F, 0xF5, 0x22, 0x01])?;
+    assert_eq!(blocks.iter().map(|b| b.block_number).collect::<Vec<u32>>(), vec![270325, 270327, 270330]);
+    assert!(blocks.iter().all(|b| b.bins.iter().all(|x| *x == 0)));
+
+    // Empty blocks wrap around within their ring
+    let blocks = decode_blocks(&[0x04, 0x20, 0x71, 0x21])?;
+    assert_eq!(blocks.iter().map(|b| b.block_number).collect::<Vec<u32>>(), vec![270449, 270001]);
+
+    // Scaled blocks in the southern hemisphere extend south from the ring's edge nearest the equator
+    let blocks = decode_blocks(&[0x54, 0x1F, 0xF5, 0x01])?;
+    assert_eq!(blocks[0].scale, 5);
+    assert!((blocks[0].north_lat_deg + 40.0).abs() < 1e-4);
+    assert!((blocks[0].south_lat_deg() + 40.0 + 20.0/60.0).abs() < 1e-4);
+    assert!((blocks[0].width_deg - 4.0).abs() < 1e-4);
+
+    // Bins have to add up to a whole block
+    assert!(decode_blocks(&[0x84, 0x1F, 0xF5, 0xF8]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn nexrad_mosaic() -> Result<(), Gdl90Error> {
+    use std::time::{Duration, SystemTime};
+    use crate::uplink_data::mosaic::{NexradMosaic, NO_DATA};
+    use crate::uplink_data::nexrad::decode_blocks;
+
+    let t0 = SystemTime::now();
+    let mut mosaic = NexradMosaic::new(Duration::from_secs(60));
+
+    // A regional block at 40N 100W, and a CONUS block at 5 times the scale with intensity 1 everywhere that covers
+    // the same area and more.  The regional block is drawn on top even though it's older
+    let regional = decode_blocks(&[0x84, 0x1F, 0xF5, 0xF8, 0xF8, 0xFB, 0xF5, 0x07])?.remove(0);
+    let conus = decode_blocks(&[0x94, 0x1F, 0xF5, 0xF9, 0xF9, 0xF9, 0xF9])?.remove(0);
+    mosaic.insert_block(regional, t0);
+    mosaic.insert_block(conus, t0 + Duration::from_secs(10));
+    assert_eq!(mosaic.len(), 2);
+
+    let raster = mosaic.raster().unwrap();
+    assert!((raster.north_lat_deg - (40.0 + 1.0/3.0)).abs() < 1e-4);
+    assert_eq!((raster.west_lon_deg, raster.south_lat_deg, raster.east_lon_deg), (-100.0, 40.0, -96.0));
+    assert_eq!((raster.width, raster.height), (160, 20));
+    assert_eq!(raster.get(40.01, -99.99), Some(5));
+    assert_eq!(raster.get(40.01, -99.21), Some(7));
+    assert_eq!(raster.get(40.06, -99.99), Some(0));
+    assert_eq!(raster.get(40.01, -99.19), Some(1));
+    assert_eq!(raster.get(40.3, -96.01), Some(1));
+    assert_eq!(raster.get(39.99, -99.99), None);
+
+    let raster = mosaic.raster_within(41.0, -101.0, 39.0, -99.0);
+    assert_eq!((raster.width, raster.height), (80, 120));
+    assert_eq!(raster.get(40.01, -99.99), Some(5));
+    assert_eq!(raster.get(40.5, -100.5), Some(NO_DATA));
+
+    let mut pgm:Vec<u8> = vec![];
+    raster.write_pgm(&mut pgm).unwrap();
+    assert!(pgm.starts_with(b"P5\n80 120\n255\n"));
+    assert_eq!(pgm.len(), 14 + 80*120);
+    assert_eq!(pgm.iter().max(), Some(&255));
+
+    // The regional block expires first
+    assert_eq!(mosaic.expire(t0 + Duration::from_secs(65)), 1);
+    let raster = mosaic.raster().unwrap();
+    assert_eq!(raster.get(40.01, -99.99), Some(1));
+    assert_eq!(mosaic.expire(t0 + Duration::from_secs(75)), 1);
+    assert!(mosaic.raster().is_none());
+
+    Ok(())
+}
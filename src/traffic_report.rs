@@ -2,28 +2,43 @@
 extern crate byteorder;
 extern crate serde;
 
+use std::fmt;
 use std::io::Cursor;
 use std::time::SystemTime;
 
 use byteorder::{BigEndian, ReadBytesExt};
 use serde::{Serialize, Deserialize};
 
-const LAT_LON_SCALE:f32 = 2.1457672119140625e-05;
+use crate::Gdl90Error;
+use crate::error::truncated;
+use crate::integrity::{Nacp, Nic};
+use crate::uat_report::EmergencyStatus;
 
-#[derive(Debug, Serialize, Deserialize)]
+// Length of a traffic or ownship report, not counting the message ID
+pub const LENGTH:usize = 27;
+
+// 180 degrees spread over 2^23 counts
+pub(crate) const LAT_LON_SCALE:f32 = 180.0 / 8388608.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TrafficReport {
-	pub status_byte: u8,
+	pub traffic_alert: bool,
+	pub address_type: AddressType,
 	pub participant_address: u32,
-	pub latitude_deg: f32,							// TODO: turn into an Option<f32> that can be None based on the NIC flag
-	pub longitude_deg: f32,							// TODO: turn into an Option<f32> that can be None based on the NIC flag
-	pub pres_altitude_ft: f32,						// TODO: turn into an Option<f32> that is None in the case of 0xFFF
+	pub latitude_deg: Option<f32>,					// None when the NIC is 0, meaning there's no valid position
+	pub longitude_deg: Option<f32>,					// None when the NIC is 0, meaning there's no valid position
+	pub pres_altitude_ft: Option<f32>,				// None in the case of 0xFFF
+	pub airborne: bool,
+	pub report_extrapolated: bool,
+	pub track_type: TrackType,						// Says whether track_heading_deg is a track or a heading
 	pub nav_integrity_category: u8,
 	pub nav_accuracy_category_for_position: u8,
-	pub horz_velocity_kts: f32,						// TODO: turn into an Option<f32> that is None in the case of 0xFFF
-	pub vert_velocity_fpm: f32,						// TODO: turn into an Option<f32> that is None in the case of 0x800
+	pub horz_velocity_kts: Option<f32>,				// None in the case of 0xFFF
+	pub vert_velocity_fpm: Option<f32>,				// None in the case of 0x800
 	pub track_heading_deg: f32,
 	pub emitter_category: EmitterCategory,
 	pub callsign:String,
+	pub emergency_status: EmergencyStatus,
 	pub recv_time:SystemTime,
 }
 
@@ -48,51 +63,280 @@ pub enum EmitterCategory {
 	PointObstacle,
 	ClusterObstacle,
 	LineObstacle,
-	ReservedOrUnassigned,
+	ReservedOrUnassigned(u8),
+}
+
+// Vertical speed at which a traffic display shows a climb or descent arrow
+pub const VERTICAL_TREND_FPM:f32 = 500.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum VerticalTrend {
+	Level,
+	Climbing,
+	Descending,
+}
+
+// Altitude of a target relative to ownship in hundreds of feet, displayed like "+05↑" for a target 500 ft above and
+// climbing
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct RelativeAltitude {
+	pub hundreds_ft: i32,
+	pub trend: VerticalTrend,
+}
+
+impl fmt::Display for RelativeAltitude {
+	fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+		let arrow:&str = match self.trend {
+			VerticalTrend::Level      => "",
+			VerticalTrend::Climbing   => "\u{2191}",
+			VerticalTrend::Descending => "\u{2193}",
+		};
+		write!(f, "{:+03}{}", self.hundreds_ft, arrow)
+	}
+}
+
+// Type of the participant address, from the low nibble of the status byte
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressType {
+	AdsbIcao,
+	AdsbSelfAssigned,
+	TisbIcao,
+	TisbTrackFile,
+	SurfaceVehicle,
+	GroundStationBeacon,
+	Reserved(u8),
+}
+
+impl AddressType {
+
+	pub fn from_u8(x:u8) -> AddressType {
+		match x {
+			0 => AddressType::AdsbIcao,
+			1 => AddressType::AdsbSelfAssigned,
+			2 => AddressType::TisbIcao,
+			3 => AddressType::TisbTrackFile,
+			4 => AddressType::SurfaceVehicle,
+			5 => AddressType::GroundStationBeacon,
+			x => AddressType::Reserved(x),
+		}
+	}
+
+	pub fn to_u8(self) -> u8 {
+		match self {
+			AddressType::AdsbIcao            => 0,
+			AddressType::AdsbSelfAssigned    => 1,
+			AddressType::TisbIcao            => 2,
+			AddressType::TisbTrackFile       => 3,
+			AddressType::SurfaceVehicle      => 4,
+			AddressType::GroundStationBeacon => 5,
+			AddressType::Reserved(x)         => x,
+		}
+	}
+
+}
+
+// How the track angle in a report should be interpreted.  GDL90 traffic reports and UAT ADS-B state vectors use
+// the same two-bit encoding
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum TrackType {
+	NotValid,
+	TrueTrack,
+	MagneticHeading,
+	TrueHeading,
+}
+
+impl TrackType {
+
+	pub fn from_u8(x:u8) -> TrackType {
+		match x & 0x03 {
+			0 => TrackType::NotValid,
+			1 => TrackType::TrueTrack,
+			2 => TrackType::MagneticHeading,
+			_ => TrackType::TrueHeading,
+		}
+	}
+
+	pub fn to_u8(self) -> u8 {
+		match self {
+			TrackType::NotValid        => 0,
+			TrackType::TrueTrack       => 1,
+			TrackType::MagneticHeading => 2,
+			TrackType::TrueHeading     => 3,
+		}
+	}
+
+}
+
+impl EmitterCategory {
+
+	// GDL90 traffic reports and UAT ADS-B Mode Status use the same emitter category codes
+	pub fn from_u8(x:u8) -> EmitterCategory {
+		match x {
+			0  => EmitterCategory::NotAvailable,
+			1  => EmitterCategory::Light,
+			2  => EmitterCategory::Small,
+			3  => EmitterCategory::Large,
+			4  => EmitterCategory::HighVortexLarge,
+			5  => EmitterCategory::Heavy,
+			6  => EmitterCategory::HighlyManeuverable,
+			7  => EmitterCategory::Rotorcraft,
+			9  => EmitterCategory::GliderOrSailplane,
+			10 => EmitterCategory::LighterThanAir,
+			11 => EmitterCategory::Parachutist,
+			12 => EmitterCategory::Ultralight,
+			14 => EmitterCategory::UnmannedAerialVehicle,
+			15 => EmitterCategory::SpaceOrTransatmospheric,
+			17 => EmitterCategory::SurfaceEmergencyVehicle,
+			18 => EmitterCategory::SurfaceServiceVehicle,
+			19 => EmitterCategory::PointObstacle,
+			20 => EmitterCategory::ClusterObstacle,
+			21 => EmitterCategory::LineObstacle,
+			x  => EmitterCategory::ReservedOrUnassigned(x),
+		}
+	}
+
+	pub fn to_u8(self) -> u8 {
+		match self {
+			EmitterCategory::NotAvailable            => 0,
+			EmitterCategory::Light                   => 1,
+			EmitterCategory::Small                   => 2,
+			EmitterCategory::Large                   => 3,
+			EmitterCategory::HighVortexLarge         => 4,
+			EmitterCategory::Heavy                   => 5,
+			EmitterCategory::HighlyManeuverable      => 6,
+			EmitterCategory::Rotorcraft              => 7,
+			EmitterCategory::GliderOrSailplane       => 9,
+			EmitterCategory::LighterThanAir          => 10,
+			EmitterCategory::Parachutist             => 11,
+			EmitterCategory::Ultralight              => 12,
+			EmitterCategory::UnmannedAerialVehicle   => 14,
+			EmitterCategory::SpaceOrTransatmospheric => 15,
+			EmitterCategory::SurfaceEmergencyVehicle => 17,
+			EmitterCategory::SurfaceServiceVehicle   => 18,
+			EmitterCategory::PointObstacle           => 19,
+			EmitterCategory::ClusterObstacle         => 20,
+			EmitterCategory::LineObstacle            => 21,
+			EmitterCategory::ReservedOrUnassigned(x) => x,
+		}
+	}
+
+}
+
+impl Default for TrafficReport {
+	fn default() -> Self { Self::new() }
 }
 
 impl TrafficReport {
 
 	pub fn new() -> TrafficReport {
 		TrafficReport {
-			status_byte: 0, participant_address: 0,
-			latitude_deg: 0.0, longitude_deg: 0.0,
-			pres_altitude_ft: 0.0, nav_integrity_category: 0,
-			nav_accuracy_category_for_position: 0, horz_velocity_kts: 0.0,
-			vert_velocity_fpm: 0.0, track_heading_deg: 0.0,
+			traffic_alert: false, address_type: AddressType::AdsbIcao, participant_address: 0,
+			airborne: true, report_extrapolated: false, track_type: TrackType::TrueTrack,
+			latitude_deg: None, longitude_deg: None,
+			pres_altitude_ft: None, nav_integrity_category: 0,
+			nav_accuracy_category_for_position: 0, horz_velocity_kts: None,
+			vert_velocity_fpm: None, track_heading_deg: 0.0,
 			emitter_category: EmitterCategory::NotAvailable, 
 			callsign: String::new(),
+			emergency_status: EmergencyStatus::NoEmergency,
 			recv_time: SystemTime::now(),
 		}
 	}
 
-	pub fn distance_nm_to(&self, other:&TrafficReport) -> f32 {
-		let dist_h:f32 = crate::util::lat_lon_dist_nm(self.latitude_deg, self.longitude_deg, other.latitude_deg, other.longitude_deg);
-		let dist_v:f32 = (other.pres_altitude_ft - self.pres_altitude_ft) / crate::FEET_PER_NM;
+	pub fn nic(&self) -> Nic {
+		Nic::from_u8(self.nav_integrity_category)
+	}
+
+	pub fn nacp(&self) -> Nacp {
+		Nacp::from_u8(self.nav_accuracy_category_for_position)
+	}
 
-		(dist_v.powi(2) + dist_h.powi(2)).sqrt()
+	// Horizontal containment radius from the NIC, or None if it's unknown
+	pub fn containment_radius_m(&self) -> Option<f32> {
+		self.nic().containment_radius_m()
 	}
 
-	pub fn project(&self, dt_sec:f32) -> TrafficReport {
+	// 95% bound on the horizontal position error from the NACp, or None if it's unknown
+	pub fn position_uncertainty_m(&self) -> Option<f32> {
+		self.nacp().epu_m()
+	}
+
+	// None if either position is unknown.  If either altitude is unknown, only the horizontal distance is used
+	pub fn distance_nm_to(&self, other:&TrafficReport) -> Option<f32> {
+		let dist_h:f32 = crate::util::lat_lon_dist_nm(self.latitude_deg?, self.longitude_deg?, other.latitude_deg?, other.longitude_deg?);
+		let dist_v:f32 = match (self.pres_altitude_ft, other.pres_altitude_ft) {
+			(Some(alt1), Some(alt2)) => (alt2 - alt1) / crate::FEET_PER_NM,
+			_                        => 0.0,
+		};
+
+		Some((dist_v.powi(2) + dist_h.powi(2)).sqrt())
+	}
+
+	// Initial bearing in degrees true from this report's position to the other's
+	pub fn bearing_deg_to(&self, other:&TrafficReport) -> Option<f32> {
+		Some(crate::util::initial_bearing_deg(self.latitude_deg?, self.longitude_deg?, other.latitude_deg?, other.longitude_deg?))
+	}
+
+	// Bearing to the other report relative to this report's track or heading.  A magnetic heading needs the magnetic
+	// variation, positive east, to compare with the true bearing; it's ignored for true tracks and headings.  None if
+	// the track isn't valid, or if it's magnetic and there's no variation
+	pub fn relative_bearing_deg_to(&self, other:&TrafficReport, mag_var_deg:Option<f32>) -> Option<f32> {
+		let true_track_deg:f32 = match self.track_type {
+			TrackType::NotValid        => return None,
+			TrackType::MagneticHeading => self.track_heading_deg + mag_var_deg?,
+			_                          => self.track_heading_deg,
+		};
+		Some(crate::util::relative_bearing_deg(self.bearing_deg_to(other)?, true_track_deg))
+	}
 
-		let dist_m   = self.horz_velocity_kts * (dt_sec / 3600.0) * crate::METERS_PER_NM;
-		let brng_rad = self.track_heading_deg * crate::RAD_PER_DEG;
+	// Clock position of the other report as seen from this one, e.g. 2 for "traffic, 2 o'clock"
+	pub fn clock_position_of(&self, other:&TrafficReport, mag_var_deg:Option<f32>) -> Option<u8> {
+		Some(crate::util::clock_position(self.relative_bearing_deg_to(other, mag_var_deg)?))
+	}
 
-		let delta:f32 = dist_m / crate::R;
-		let phi1:f32  = self.latitude_deg * crate::RAD_PER_DEG;
-		let lam1:f32  = self.longitude_deg * crate::RAD_PER_DEG;
+	// Altitude of the other report relative to this one as shown on a traffic display
+	pub fn relative_altitude_of(&self, other:&TrafficReport) -> Option<RelativeAltitude> {
+		let dz_ft:f32 = other.pres_altitude_ft? - self.pres_altitude_ft?;
+		let trend = match other.vert_velocity_fpm {
+			Some(vv_fpm) if vv_fpm >=  VERTICAL_TREND_FPM => VerticalTrend::Climbing,
+			Some(vv_fpm) if vv_fpm <= -VERTICAL_TREND_FPM => VerticalTrend::Descending,
+			_                                             => VerticalTrend::Level,
+		};
+		Some(RelativeAltitude{ hundreds_ft: (dz_ft / 100.0).round() as i32, trend })
+	}
 
-		let phi2:f32 = (phi1.sin()*delta.cos() + phi1.cos()*delta.sin()*brng_rad.cos()).asin();
-		let lam2:f32 = lam1 + (brng_rad.sin()*delta.sin()*phi1.sin()).atan2(delta.cos() - phi1.sin()*phi2.sin());
+	// Latitude and longitude at a range and bearing from this report's position
+	pub fn lat_lon_at(&self, range_nm:f32, bearing_deg:f32) -> Option<(f32, f32)> {
+		Some(crate::util::destination_lat_lon(self.latitude_deg?, self.longitude_deg?, range_nm, bearing_deg))
+	}
 
-		let latitude_deg:f32     = phi2 / crate::RAD_PER_DEG;
-		let longitude_deg:f32    = lam2 / crate::RAD_PER_DEG;
-		let pres_altitude_ft:f32 = self.pres_altitude_ft + self.vert_velocity_fpm*(dt_sec/60.0);
+	// Dead-reckons the report forward in time.  Fields that can't be projected because the position or one of the
+	// velocities is unknown are carried over as they are
+	pub fn project(&self, dt_sec:f32) -> TrafficReport {
+
+		// Without a valid track there's no direction to move in, so the position stays where it is
+		let (latitude_deg, longitude_deg) = match (self.latitude_deg, self.longitude_deg, self.horz_velocity_kts) {
+			(Some(lat_deg), Some(lon_deg), Some(horz_velocity_kts)) if self.track_type != TrackType::NotValid => {
+				let range_nm:f32 = horz_velocity_kts * (dt_sec / 3600.0);
+				let (lat_deg, lon_deg) = crate::util::destination_lat_lon(lat_deg, lon_deg, range_nm, self.track_heading_deg);
+				(Some(lat_deg), Some(lon_deg))
+			},
+			_ => (self.latitude_deg, self.longitude_deg),
+		};
+
+		let pres_altitude_ft:Option<f32> = match (self.pres_altitude_ft, self.vert_velocity_fpm) {
+			(Some(alt_ft), Some(vv_fpm)) => Some(alt_ft + vv_fpm*(dt_sec/60.0)),
+			(alt_ft, _)                  => alt_ft,
+		};
 
 		TrafficReport {
-			status_byte:         self.status_byte, 
+			traffic_alert:       self.traffic_alert,
+			address_type:        self.address_type,
 			participant_address: self.participant_address,
-			latitude_deg, longitude_deg, pres_altitude_ft, 
+			latitude_deg, longitude_deg, pres_altitude_ft,
+			airborne:            self.airborne,
+			report_extrapolated: true,
+			track_type:          self.track_type,
 			nav_integrity_category:             self.nav_integrity_category,
 			nav_accuracy_category_for_position: self.nav_accuracy_category_for_position, 
 			horz_velocity_kts: self.horz_velocity_kts, 
@@ -100,86 +344,119 @@ impl TrafficReport {
 			track_heading_deg: self.track_heading_deg,
 			emitter_category:  self.emitter_category, 
 			callsign:          self.callsign.clone(),
+			emergency_status:  self.emergency_status,
 			recv_time:         self.recv_time,
 		}
 	}
 
-	pub fn from_slice(data: &[u8]) -> std::io::Result<TrafficReport> {
+	// Decodes the body of a traffic report (message ID 20); ownship reports (message ID 10) use the same format
+	pub fn from_slice(data: &[u8]) -> Result<TrafficReport, Gdl90Error> {
+		let t = truncated(20, LENGTH, data);
 		let mut rdr = Cursor::new(data);
-		let status_byte:u8              = rdr.read_u8()?;
+		// Traffic alert status in the upper nibble, address type in the lower
+		let status_byte:u8              = rdr.read_u8().map_err(&t)?;
+		let traffic_alert:bool          = status_byte >> 4 == 1;
+		let address_type:AddressType    = AddressType::from_u8(status_byte & 0x0F);
 		
-		let participant_address_msb:u16 = rdr.read_u16::<BigEndian>()?;
-		let participant_address_lsb:u8  = rdr.read_u8()?;
+		let participant_address_msb:u16 = rdr.read_u16::<BigEndian>().map_err(&t)?;
+		let participant_address_lsb:u8  = rdr.read_u8().map_err(&t)?;
 		let participant_address:u32     = (participant_address_msb as u32 * 256) + (participant_address_lsb as u32);
 
-		let latitude_raw_msb:i16 = rdr.read_i16::<BigEndian>()?;
-		let latitude_raw_lsb:u8  = rdr.read_u8()?;
+		let latitude_raw_msb:i16 = rdr.read_i16::<BigEndian>().map_err(&t)?;
+		let latitude_raw_lsb:u8  = rdr.read_u8().map_err(&t)?;
 		let latitude_raw:i32     = (latitude_raw_msb as i32 * 256) + (latitude_raw_lsb as i32);
-		let latitude_deg:f32     = (latitude_raw as f32) * LAT_LON_SCALE;
 
-		let longitude_raw_msb:i16 = rdr.read_i16::<BigEndian>()?;
-		let longitude_raw_lsb:u8  = rdr.read_u8()?;
+		let longitude_raw_msb:i16 = rdr.read_i16::<BigEndian>().map_err(&t)?;
+		let longitude_raw_lsb:u8  = rdr.read_u8().map_err(&t)?;
 		let longitude_raw:i32     = (longitude_raw_msb as i32 * 256) + (longitude_raw_lsb as i32);
-		let longitude_deg:f32     = (longitude_raw as f32) * LAT_LON_SCALE;
 
-		let pres_altitude_misc_raw:u16 = rdr.read_u16::<BigEndian>()?;
+		let pres_altitude_misc_raw:u16 = rdr.read_u16::<BigEndian>().map_err(&t)?;
 		let pres_altitude_raw:u16      = pres_altitude_misc_raw >> 4;
-		let pres_altitude_ft:f32       = ((pres_altitude_raw as f32) * 25.0) - 1000.0;
-		// TODO: decode miscellaneous indicators
+		let pres_altitude_ft:Option<f32> = if pres_altitude_raw == 0xFFF { None } else { Some(((pres_altitude_raw as f32) * 25.0) - 1000.0) };
+		// Miscellaneous indicators: airborne, extrapolated rather than updated, and the track type
+		let airborne:bool              = pres_altitude_misc_raw & 0x0008 != 0;
+		let report_extrapolated:bool   = pres_altitude_misc_raw & 0x0004 != 0;
+		let track_type:TrackType       = TrackType::from_u8(pres_altitude_misc_raw as u8);
 
-		let nic_nacp_raw:u8                       = rdr.read_u8()?;
+		let nic_nacp_raw:u8                       = rdr.read_u8().map_err(&t)?;
 		let nav_integrity_category:u8             = nic_nacp_raw >> 4;
 		let nav_accuracy_category_for_position:u8 = nic_nacp_raw & 0x0F;
 
-		let velocity_raw_msb:u16   =  rdr.read_u16::<BigEndian>()?;
-		let velocity_raw_lsb:u8    =  rdr.read_u8()?;    
+		// A NIC of zero means the integrity is unknown, in which case the position fields aren't valid
+		let (latitude_deg, longitude_deg) = if nav_integrity_category == 0 { (None, None) } else {
+			(Some((latitude_raw as f32) * LAT_LON_SCALE), Some((longitude_raw as f32) * LAT_LON_SCALE))
+		};
+
+		let velocity_raw_msb:u16   =  rdr.read_u16::<BigEndian>().map_err(&t)?;
+		let velocity_raw_lsb:u8    =  rdr.read_u8().map_err(&t)?;    
 		let velocity_raw:u32       = (velocity_raw_msb as u32 * 256) + (velocity_raw_lsb as u32);
 		let horz_velocity_raw:u32  =  velocity_raw >> 12;
 		let vert_velocity_raw:u32  =  velocity_raw &  0x000007FF;
 		let vert_velocity_pos:bool = (velocity_raw &  0x00000800) == 0;
-		let horz_velocity_kts:f32  =  horz_velocity_raw as f32;
-		let vert_velocity_fpm:f32  = if vert_velocity_pos {  (vert_velocity_raw               as f32) *  64.0 }
-									 else                 { ((vert_velocity_raw ^ 0x000007FF) as f32) * -64.0 }; 
+		let horz_velocity_kts:Option<f32> = if horz_velocity_raw == 0xFFF { None } else { Some(horz_velocity_raw as f32) };
+		let vert_velocity_fpm:Option<f32> = if velocity_raw & 0x00000FFF == 0x800 { None }
+									 else if vert_velocity_pos { Some(  (vert_velocity_raw                     as f32) *  64.0) }
+									 else                      { Some((((vert_velocity_raw ^ 0x000007FF) + 1) as f32) * -64.0) };
 
-		let track_heading_raw:u8   = rdr.read_u8()?;
+		let track_heading_raw:u8   = rdr.read_u8().map_err(&t)?;
 		let track_heading_deg:f32  = (track_heading_raw as f32) * (360.0 / 256.0);    
 
-		let emitter_category:EmitterCategory = match rdr.read_u8()? {
-			0  => EmitterCategory::NotAvailable,
-			1  => EmitterCategory::Light,
-			2  => EmitterCategory::Small,
-			3  => EmitterCategory::Large,
-			4  => EmitterCategory::HighVortexLarge,
-			5  => EmitterCategory::Heavy,
-			6  => EmitterCategory::HighlyManeuverable,
-			7  => EmitterCategory::Rotorcraft,
-			9  => EmitterCategory::GliderOrSailplane,
-			10 => EmitterCategory::LighterThanAir,
-			11 => EmitterCategory::Parachutist,
-			12 => EmitterCategory::Ultralight,
-			14 => EmitterCategory::UnmannedAerialVehicle,
-			15 => EmitterCategory::SpaceOrTransatmospheric,
-			17 => EmitterCategory::SurfaceEmergencyVehicle,
-			18 => EmitterCategory::SurfaceServiceVehicle,
-			19 => EmitterCategory::PointObstacle,
-			20 => EmitterCategory::ClusterObstacle,
-			21 => EmitterCategory::LineObstacle,
-			_  => EmitterCategory::ReservedOrUnassigned,
-		};
+		let emitter_category:EmitterCategory = EmitterCategory::from_u8(rdr.read_u8().map_err(&t)?);
 
 		let mut callsign:String = String::new();
 		for _ in 0..8 {
-			let c = rdr.read_u8()?;
+			let c = rdr.read_u8().map_err(&t)?;
 			if c != 0x00 && c != 0x20 {
 				callsign.push(c as char);
 			}
 		}
 
+		// Emergency/priority code in the upper nibble, the rest is spare
+		let emergency_status:EmergencyStatus = EmergencyStatus::from_u8(rdr.read_u8().map_err(&t)? >> 4);
+
 		let recv_time = SystemTime::now();
 
-		Ok(TrafficReport{ status_byte, participant_address, latitude_deg, longitude_deg, pres_altitude_ft,
-			nav_integrity_category, nav_accuracy_category_for_position, horz_velocity_kts, vert_velocity_fpm,
-			track_heading_deg, emitter_category, callsign, recv_time})
+		Ok(TrafficReport{ traffic_alert, address_type, participant_address, latitude_deg, longitude_deg, pres_altitude_ft,
+			airborne, report_extrapolated, track_type, nav_integrity_category, nav_accuracy_category_for_position,
+			horz_velocity_kts, vert_velocity_fpm,
+			track_heading_deg, emitter_category, callsign, emergency_status, recv_time})
+	}
+
+	// The inverse of from_slice; produces the 27 bytes that follow the message ID
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut ans:Vec<u8> = Vec::with_capacity(LENGTH);
+		ans.push((if self.traffic_alert { 0x10 } else { 0x00 }) | (self.address_type.to_u8() & 0x0F));
+		ans.extend_from_slice(&self.participant_address.to_be_bytes()[1..]);
+
+		let latitude_raw:i32  = self.latitude_deg.map(|lat| (lat / LAT_LON_SCALE).round() as i32).unwrap_or(0);
+		let longitude_raw:i32 = self.longitude_deg.map(|lon| (lon / LAT_LON_SCALE).round() as i32).unwrap_or(0);
+		ans.extend_from_slice(&latitude_raw.to_be_bytes()[1..]);
+		ans.extend_from_slice(&longitude_raw.to_be_bytes()[1..]);
+
+		let pres_altitude_raw:u16 = self.pres_altitude_ft.map(|alt| ((alt + 1000.0) / 25.0).round().clamp(0.0, 4094.0) as u16).unwrap_or(0xFFF);
+		let misc_raw:u16 = (if self.airborne { 0x8 } else { 0x0 }) | (if self.report_extrapolated { 0x4 } else { 0x0 }) | (self.track_type.to_u8() as u16);
+		ans.extend_from_slice(&((pres_altitude_raw << 4) | misc_raw).to_be_bytes());
+
+		// A NIC of zero is how the position is marked as not valid, whatever the NIC would otherwise be
+		let nic:u8 = if self.latitude_deg.is_some() && self.longitude_deg.is_some() { self.nav_integrity_category & 0x0F } else { 0 };
+		ans.push((nic << 4) | (self.nav_accuracy_category_for_position & 0x0F));
+
+		let horz_velocity_raw:u32 = self.horz_velocity_kts.map(|v| v.round().clamp(0.0, 4094.0) as u32).unwrap_or(0xFFF);
+		let vert_velocity_raw:i32 = self.vert_velocity_fpm.map(|v| (v / 64.0).round().clamp(-2047.0, 2047.0) as i32).unwrap_or(-2048);
+		let velocity_raw:u32      = (horz_velocity_raw << 12) | ((vert_velocity_raw as u32) & 0x00000FFF);
+		ans.extend_from_slice(&velocity_raw.to_be_bytes()[1..]);
+
+		ans.push(((self.track_heading_deg * (256.0 / 360.0)).round() as i32).rem_euclid(256) as u8);
+		ans.push(self.emitter_category.to_u8());
+
+		let mut callsign:Vec<u8> = self.callsign.bytes().take(8).collect();
+		callsign.resize(8, 0x20);
+		ans.extend(callsign);
+
+		// Emergency/priority code and spare bits
+		ans.push((self.emergency_status.to_u8() & 0x0F) << 4);
+
+		ans
 	}
 
 }
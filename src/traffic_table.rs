@@ -0,0 +1,147 @@
+extern crate serde;
+
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::Entry;
+use std::time::{Duration, SystemTime};
+
+use serde::{Serialize, Deserialize};
+
+use crate::traffic_report::{AddressType, TrafficReport};
+
+// Targets are identified by their address and its type, since e.g. a TIS-B track file number can have the same value
+// as an unrelated ICAO address
+pub type TargetKey = (u32, AddressType);
+
+// A position from a previous report
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+	pub latitude_deg: f32,
+	pub longitude_deg: f32,
+	pub pres_altitude_ft: Option<f32>,
+	pub time: SystemTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Target {
+	// The most recent report as received, except that the callsign is filled in from an earlier report if this one
+	// didn't include it
+	pub last_report: TrafficReport,
+	// The most recent report dead-reckoned to the time of the last call to TrafficTable::tick
+	pub report: TrafficReport,
+	// True if report has been projected forward from last_report
+	pub extrapolated: bool,
+	// Positions from the most recent reports, oldest first, including the position in last_report
+	pub history: VecDeque<TrackPoint>,
+	pub report_count: u32,
+}
+
+// Merges traffic reports into a table of targets
+#[derive(Debug)]
+pub struct TrafficTable {
+	targets: HashMap<TargetKey, Target>,
+	history_len: usize,
+	timeout: Duration,
+}
+
+impl TrafficTable {
+
+	// history_len is the number of positions kept for each target, and targets are removed by tick once this long has
+	// passed without a report
+	pub fn new(history_len:usize, timeout:Duration) -> TrafficTable {
+		TrafficTable{ targets: HashMap::new(), history_len, timeout }
+	}
+
+	// Adds a report to the table, merging it with the target's earlier reports if there are any
+	pub fn update(&mut self, mut report:TrafficReport) -> &Target {
+		let key:TargetKey = (report.participant_address, report.address_type);
+		let history_len:usize = self.history_len;
+
+		let point = match (report.latitude_deg, report.longitude_deg) {
+			(Some(latitude_deg), Some(longitude_deg)) => Some(TrackPoint {
+				latitude_deg, longitude_deg,
+				pres_altitude_ft: report.pres_altitude_ft,
+				time:             report.recv_time,
+			}),
+			_ => None,
+		};
+
+		let target:&mut Target = match self.targets.entry(key) {
+			Entry::Occupied(entry) => {
+				let target = entry.into_mut();
+				if report.callsign.is_empty() {
+					report.callsign = target.last_report.callsign.clone();
+				}
+				target.report       = report.clone();
+				target.last_report  = report;
+				target.extrapolated = false;
+				target.report_count += 1;
+				target
+			},
+			Entry::Vacant(entry) => entry.insert(Target {
+				report:       report.clone(),
+				last_report:  report,
+				extrapolated: false,
+				history:      VecDeque::with_capacity(history_len),
+				report_count: 1,
+			}),
+		};
+
+		if let Some(point) = point {
+			target.history.push_back(point);
+		}
+		while target.history.len() > history_len {
+			target.history.pop_front();
+		}
+		target
+	}
+
+	// Removes targets that haven't been heard from within the timeout and dead-reckons the rest to the given time.
+	// Returns the keys of the targets that were removed
+	pub fn tick(&mut self, now:SystemTime) -> Vec<TargetKey> {
+		let timeout:Duration = self.timeout;
+		let age = |target:&Target| now.duration_since(target.last_report.recv_time).unwrap_or(Duration::from_secs(0));
+
+		let expired:Vec<TargetKey> = self.targets.iter()
+			.filter(|(_, target)| age(target) > timeout)
+			.map(|(key, _)| *key)
+			.collect();
+		for key in &expired {
+			self.targets.remove(key);
+		}
+
+		for target in self.targets.values_mut() {
+			let dt_sec:f32 = age(target).as_secs_f32();
+			if dt_sec > 0.0 {
+				target.report       = target.last_report.project(dt_sec);
+				target.extrapolated = true;
+			}
+		}
+
+		expired
+	}
+
+	pub fn get(&self, participant_address:u32, address_type:AddressType) -> Option<&Target> {
+		self.targets.get(&(participant_address, address_type))
+	}
+
+	pub fn remove(&mut self, participant_address:u32, address_type:AddressType) -> Option<Target> {
+		self.targets.remove(&(participant_address, address_type))
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &Target> {
+		self.targets.values()
+	}
+
+	pub fn len(&self) -> usize {
+		self.targets.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.targets.is_empty()
+	}
+
+	pub fn clear(&mut self) {
+		self.targets.clear();
+	}
+
+}
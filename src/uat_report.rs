@@ -0,0 +1,372 @@
+extern crate serde;
+
+use serde::{Serialize, Deserialize};
+
+use crate::Gdl90Error;
+use crate::traffic_report::{EmitterCategory, TrackType};
+
+// Lengths of the UAT ADS-B payloads carried by the Basic (ID 30) and Long (ID 31) pass-through reports
+pub const BASIC_LENGTH:usize = 18;
+pub const LONG_LENGTH:usize  = 34;
+
+// 360 degrees spread over 2^24 counts
+const LAT_LON_SCALE:f32 = 360.0 / 16777216.0;
+
+// Characters used by the base-40 callsign encoding in the Mode Status element
+const BASE40:&[u8; 40] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ  ..";
+
+// A UAT ADS-B message payload, decoded based on section 2.2.4.5 of DO-282B.  Reports are read-only and re-encode as
+// the bytes they were decoded from, since the decoded fields don't keep every bit of the payload
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UatReport {
+	payload_type: u8,
+	address_qualifier: AddressQualifier,
+	address: u32,
+	state_vector: StateVector,
+	mode_status: Option<ModeStatus>,
+	aux_state_vector: Option<AuxStateVector>,
+	raw: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AddressQualifier {
+	AdsbIcao,
+	AdsbSelfAssigned,
+	TisbIcao,
+	TisbTrackFile,
+	SurfaceVehicle,
+	FixedBeacon,
+	AdsrIcao,
+	Reserved,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AltitudeType {
+	Barometric,
+	Geometric,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AirGroundState {
+	AirborneSubsonic,
+	AirborneSupersonic,
+	OnGround,
+	Reserved,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum EmergencyStatus {
+	NoEmergency,
+	General,
+	Medical,
+	MinimumFuel,
+	NoCommunication,
+	UnlawfulInterference,
+	DownedAircraft,
+	Reserved(u8),
+}
+
+impl EmergencyStatus {
+
+	// GDL90 traffic reports and UAT ADS-B Mode Status use the same emergency/priority codes
+	pub fn from_u8(x:u8) -> EmergencyStatus {
+		match x {
+			0 => EmergencyStatus::NoEmergency,
+			1 => EmergencyStatus::General,
+			2 => EmergencyStatus::Medical,
+			3 => EmergencyStatus::MinimumFuel,
+			4 => EmergencyStatus::NoCommunication,
+			5 => EmergencyStatus::UnlawfulInterference,
+			6 => EmergencyStatus::DownedAircraft,
+			x => EmergencyStatus::Reserved(x),
+		}
+	}
+
+	pub fn to_u8(self) -> u8 {
+		match self {
+			EmergencyStatus::NoEmergency          => 0,
+			EmergencyStatus::General              => 1,
+			EmergencyStatus::Medical              => 2,
+			EmergencyStatus::MinimumFuel          => 3,
+			EmergencyStatus::NoCommunication      => 4,
+			EmergencyStatus::UnlawfulInterference => 5,
+			EmergencyStatus::DownedAircraft       => 6,
+			EmergencyStatus::Reserved(x)          => x,
+		}
+	}
+
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateVector {
+	// None when both the latitude and longitude fields are zero, which means no position is available
+	pub latitude_deg: Option<f32>,
+	pub longitude_deg: Option<f32>,
+	pub altitude_type: AltitudeType,
+	pub altitude_ft: Option<f32>,
+	pub nav_integrity_category: u8,
+	pub air_ground_state: AirGroundState,
+
+	// Airborne targets report north and east velocity; track and ground speed are derived from them.  Targets on the
+	// ground report ground speed and a track angle whose meaning is given by track_type
+	pub north_velocity_kts: Option<f32>,
+	pub east_velocity_kts: Option<f32>,
+	pub ground_speed_kts: Option<f32>,
+	pub track_deg: Option<f32>,
+	pub track_type: TrackType,
+
+	pub vert_velocity_fpm: Option<f32>,
+	pub vert_velocity_is_geometric: bool,
+
+	// TIS-B targets carry a site ID in place of the UTC coupled bit
+	pub utc_coupled: bool,
+	pub tisb_site_id: Option<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModeStatus {
+	pub emitter_category: EmitterCategory,
+	pub callsign: String,
+	// True if the callsign is a flight ID, false if it's a Mode 3/A code
+	pub callsign_is_flight_id: bool,
+	pub emergency_status: EmergencyStatus,
+	pub uat_version: u8,
+	pub source_integrity_level: u8,
+	pub transmit_mso: u8,
+	pub nav_accuracy_category_for_position: u8,
+	pub nav_accuracy_category_for_velocity: u8,
+	pub nic_baro: bool,
+	pub cdti_capable: bool,
+	pub acas_installed: bool,
+	pub acas_ra_active: bool,
+	pub ident_active: bool,
+	pub atc_services: bool,
+	pub heading_is_magnetic: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuxStateVector {
+	// The secondary altitude is geometric if the primary is barometric and vice versa
+	pub secondary_altitude_type: AltitudeType,
+	pub secondary_altitude_ft: Option<f32>,
+}
+
+// 12-bit altitude with 25 ft resolution and a -1000 ft offset; zero means no altitude is available
+fn decode_altitude(raw:u16) -> Option<f32> {
+	if raw == 0 { None } else { Some(((raw as f32 - 1.0) * 25.0) - 1000.0) }
+}
+
+// 11-bit velocity made up of a sign bit and 10 bits of magnitude; zero magnitude means no velocity is available
+fn decode_velocity(raw:u16, supersonic:bool) -> Option<f32> {
+	let magnitude:u16 = raw & 0x03FF;
+	if magnitude == 0 {
+		return None;
+	}
+
+	let kts:f32 = (magnitude - 1) as f32 * if supersonic { 4.0 } else { 1.0 };
+	Some(if raw & 0x0400 == 0 { kts } else { -kts })
+}
+
+impl StateVector {
+
+	fn from_slice(data:&[u8]) -> StateVector {
+		let lat_raw:u32 = ((data[4] as u32) << 15) | ((data[5] as u32) << 7) | ((data[6] as u32) >> 1);
+		let lon_raw:u32 = (((data[6] & 0x01) as u32) << 23) | ((data[7] as u32) << 15) | ((data[8] as u32) << 7) | ((data[9] as u32) >> 1);
+		let (latitude_deg, longitude_deg) = if lat_raw == 0 && lon_raw == 0 {
+			(None, None)
+		} else {
+			let lat:f32 = lat_raw as f32 * LAT_LON_SCALE;
+			let lon:f32 = lon_raw as f32 * LAT_LON_SCALE;
+			(Some(if lat > 90.0 { lat - 180.0 } else { lat }), Some(if lon > 180.0 { lon - 360.0 } else { lon }))
+		};
+
+		let altitude_type = if data[9] & 0x01 == 0 { AltitudeType::Barometric } else { AltitudeType::Geometric };
+		let altitude_ft   = decode_altitude(((data[10] as u16) << 4) | ((data[11] as u16) >> 4));
+		let nav_integrity_category:u8 = data[11] & 0x0F;
+
+		let air_ground_state = match data[12] >> 6 {
+			0 => AirGroundState::AirborneSubsonic,
+			1 => AirGroundState::AirborneSupersonic,
+			2 => AirGroundState::OnGround,
+			_ => AirGroundState::Reserved,
+		};
+
+		// The two 11-bit fields after the air/ground state are either north/south and east/west velocity or ground
+		// speed and track, depending on the air/ground state
+		let first_raw:u16  = (((data[12] & 0x1F) as u16) << 6) | ((data[13] as u16) >> 2);
+		let second_raw:u16 = (((data[13] & 0x03) as u16) << 9) | ((data[14] as u16) << 1) | ((data[15] as u16) >> 7);
+
+		let mut north_velocity_kts:Option<f32> = None;
+		let mut east_velocity_kts:Option<f32>  = None;
+		let mut ground_speed_kts:Option<f32>   = None;
+		let mut track_deg:Option<f32>          = None;
+		let mut track_type:TrackType           = TrackType::NotValid;
+		let mut vert_velocity_fpm:Option<f32>  = None;
+		let mut vert_velocity_is_geometric     = false;
+
+		match air_ground_state {
+			AirGroundState::AirborneSubsonic | AirGroundState::AirborneSupersonic => {
+				let supersonic = air_ground_state == AirGroundState::AirborneSupersonic;
+				north_velocity_kts = decode_velocity(first_raw, supersonic);
+				east_velocity_kts  = decode_velocity(second_raw, supersonic);
+
+				if let (Some(n), Some(e)) = (north_velocity_kts, east_velocity_kts) {
+					ground_speed_kts = Some((n*n + e*e).sqrt());
+					track_deg        = Some(e.atan2(n).to_degrees().rem_euclid(360.0));
+					track_type       = TrackType::TrueTrack;
+				}
+
+				let vv_raw:u16 = (((data[15] & 0x7F) as u16) << 4) | ((data[16] as u16) >> 4);
+				if vv_raw & 0x01FF != 0 {
+					let fpm:f32 = ((vv_raw & 0x01FF) - 1) as f32 * 64.0;
+					vert_velocity_fpm = Some(if vv_raw & 0x0200 == 0 { fpm } else { -fpm });
+					vert_velocity_is_geometric = vv_raw & 0x0400 == 0;
+				}
+			},
+			AirGroundState::OnGround => {
+				if first_raw & 0x03FF != 0 {
+					ground_speed_kts = Some(((first_raw & 0x03FF) - 1) as f32);
+				}
+				track_type = TrackType::from_u8((second_raw >> 9) as u8);
+				if track_type != TrackType::NotValid {
+					track_deg = Some((second_raw & 0x01FF) as f32 * (360.0 / 512.0));
+				}
+			},
+			AirGroundState::Reserved => {},
+		}
+
+		let (utc_coupled, tisb_site_id) = match data[0] & 0x07 {
+			2 | 3 => (false, Some(data[16] & 0x0F)),
+			_     => (data[16] & 0x08 != 0, None),
+		};
+
+		StateVector{ latitude_deg, longitude_deg, altitude_type, altitude_ft, nav_integrity_category, air_ground_state,
+			north_velocity_kts, east_velocity_kts, ground_speed_kts, track_deg, track_type, vert_velocity_fpm,
+			vert_velocity_is_geometric, utc_coupled, tisb_site_id }
+	}
+
+}
+
+impl ModeStatus {
+
+	fn from_slice(data:&[u8]) -> ModeStatus {
+		let word = |idx:usize| ((data[idx] as u16) << 8) | (data[idx+1] as u16);
+		let (w1, w2, w3) = (word(17), word(19), word(21));
+
+		let emitter_category = EmitterCategory::from_u8(((w1 / 1600) % 40) as u8);
+		let callsign:String = [(w1/40)%40, w1%40, (w2/1600)%40, (w2/40)%40, w2%40, (w3/1600)%40, (w3/40)%40, w3%40].iter()
+			.map(|c| BASE40[*c as usize] as char)
+			.collect::<String>().trim_end().to_string();
+
+		let emergency_status = EmergencyStatus::from_u8(data[23] >> 5);
+
+		ModeStatus {
+			emitter_category, callsign, emergency_status,
+			callsign_is_flight_id:              data[26] & 0x02 != 0,
+			uat_version:                        (data[23] >> 2) & 0x07,
+			source_integrity_level:             data[23] & 0x03,
+			transmit_mso:                       data[24] >> 2,
+			nav_accuracy_category_for_position: data[25] >> 4,
+			nav_accuracy_category_for_velocity: (data[25] >> 1) & 0x07,
+			nic_baro:                           data[25] & 0x01 != 0,
+			cdti_capable:                       data[26] & 0x80 != 0,
+			acas_installed:                     data[26] & 0x40 != 0,
+			acas_ra_active:                     data[26] & 0x20 != 0,
+			ident_active:                       data[26] & 0x10 != 0,
+			atc_services:                       data[26] & 0x08 != 0,
+			heading_is_magnetic:                data[26] & 0x04 != 0,
+		}
+	}
+
+}
+
+impl AuxStateVector {
+
+	fn from_slice(data:&[u8], primary:AltitudeType) -> AuxStateVector {
+		let secondary_altitude_type = match primary {
+			AltitudeType::Barometric => AltitudeType::Geometric,
+			AltitudeType::Geometric  => AltitudeType::Barometric,
+		};
+		let secondary_altitude_ft = decode_altitude(((data[29] as u16) << 4) | ((data[30] as u16) >> 4));
+
+		AuxStateVector{ secondary_altitude_type, secondary_altitude_ft }
+	}
+
+}
+
+impl UatReport {
+
+	// Decodes the payload of a Basic (msg_id 30) or Long (msg_id 31) report, after the time of reception
+	pub fn from_slice(msg_id:u8, data:&[u8]) -> Result<UatReport, Gdl90Error> {
+		let needed:usize = if msg_id == 31 { LONG_LENGTH } else { BASIC_LENGTH };
+		if data.len() < needed {
+			// The three bytes of time of reception have already been removed
+			return Err(Gdl90Error::Truncated{ msg_id, needed: needed + 3, got: data.len() + 3 });
+		}
+
+		let payload_type:u8 = data[0] >> 3;
+		let address_qualifier = match data[0] & 0x07 {
+			0 => AddressQualifier::AdsbIcao,
+			1 => AddressQualifier::AdsbSelfAssigned,
+			2 => AddressQualifier::TisbIcao,
+			3 => AddressQualifier::TisbTrackFile,
+			4 => AddressQualifier::SurfaceVehicle,
+			5 => AddressQualifier::FixedBeacon,
+			6 => AddressQualifier::AdsrIcao,
+			_ => AddressQualifier::Reserved,
+		};
+		let address:u32 = ((data[1] as u32) << 16) | ((data[2] as u32) << 8) | (data[3] as u32);
+
+		let state_vector = StateVector::from_slice(data);
+
+		// Payload type 0 is the only basic payload; the rest are long payloads with different combinations of elements
+		let long:bool = needed == LONG_LENGTH;
+		let mode_status = match payload_type {
+			1 | 3 if long => Some(ModeStatus::from_slice(data)),
+			_             => None,
+		};
+		let aux_state_vector = match payload_type {
+			1 | 2 | 5 | 6 if long => Some(AuxStateVector::from_slice(data, state_vector.altitude_type)),
+			_                     => None,
+		};
+
+		Ok(UatReport{ payload_type, address_qualifier, address, state_vector, mode_status, aux_state_vector,
+			raw: data[..needed].to_vec() })
+	}
+
+	pub fn payload_type(&self) -> u8 {
+		self.payload_type
+	}
+
+	pub fn address_qualifier(&self) -> AddressQualifier {
+		self.address_qualifier
+	}
+
+	pub fn address(&self) -> u32 {
+		self.address
+	}
+
+	pub fn state_vector(&self) -> &StateVector {
+		&self.state_vector
+	}
+
+	// Only in long payloads of type 1 or 3
+	pub fn mode_status(&self) -> Option<&ModeStatus> {
+		self.mode_status.as_ref()
+	}
+
+	// Only in long payloads of type 1, 2, 5, or 6
+	pub fn aux_state_vector(&self) -> Option<&AuxStateVector> {
+		self.aux_state_vector.as_ref()
+	}
+
+	// The payload as it was received
+	pub fn raw(&self) -> &[u8] {
+		&self.raw
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		self.raw.clone()
+	}
+
+}
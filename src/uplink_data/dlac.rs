@@ -1,8 +1,8 @@
 
 enum State { 
-	StepOne, 
-	StepTwo(u8),
-	StepThree(u8),
+	First, 
+	Second(u8),
+	Third(u8),
 }
 
 pub struct Decoder {
@@ -24,22 +24,22 @@ fn u8_to_char(x:u8) -> char { match x {
 
 impl Decoder {
 
-	pub fn new() -> Decoder { Decoder{ state: State::StepOne, string: String::new() } }
+	pub fn new() -> Decoder { Decoder{ state: State::First, string: String::new() } }
 
 	pub fn next(&mut self, next_byte:u8) {
 		let next_state:State = match self.state {
-			State::StepOne => {
+			State::First => {
 				self.string.push(u8_to_char(next_byte >> 2));
-				State::StepTwo(next_byte % 4)
+				State::Second(next_byte % 4)
 			},
-			State::StepTwo(remainder) => {
+			State::Second(remainder) => {
 				self.string.push(u8_to_char((remainder*16) + (next_byte >> 4)));
-				State::StepThree(next_byte % 16)
+				State::Third(next_byte % 16)
 			},
-			State::StepThree(remainder) => {
+			State::Third(remainder) => {
 				self.string.push(u8_to_char((remainder*4)  + (next_byte >> 6)));
 				self.string.push(u8_to_char(next_byte % 64));
-				State::StepOne
+				State::First
 			}
 		};
 
@@ -48,7 +48,7 @@ impl Decoder {
 
 	pub fn get_result(&mut self) -> String {
 		let ans = self.string.clone();
-		self.state = State::StepOne;
+		self.state = State::First;
 		self.string.clear();
 		ans
 	}
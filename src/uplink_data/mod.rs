@@ -2,32 +2,47 @@
 extern crate byteorder;
 extern crate serde;
 
-use std::io::{Cursor, Error, ErrorKind};
+use std::io::Cursor;
 
 use byteorder::{BigEndian, ReadBytesExt};
 use serde::{Serialize, Deserialize};
 
+use crate::Gdl90Error;
+use crate::error::truncated;
+
 const LAT_LON_LSB:f32 = 0.000021458;
 
 mod dlac;
+pub mod mosaic;
+pub mod nexrad;
+pub mod reassembly;
 pub mod text;
 
+// An uplink payload from a ground station.  Payloads are only made by decoding, and encode back to the received bytes
+// rather than from the frames, which can't all be re-encoded
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Payload {
-	pub ground_station_latitude_deg:f32,
-	pub ground_station_longitude_deg:f32,
-	pub application_data: Vec<Frame>,
+	ground_station_latitude_deg:f32,
+	ground_station_longitude_deg:f32,
+	application_data: Vec<Frame>,
+	raw: Vec<u8>,
 }
 
 impl Payload {
 
-	pub fn new(mut payload:Vec<u8>) -> std::io::Result<Payload> {
+	pub fn new(mut payload:Vec<u8>) -> Result<Payload, Gdl90Error> {
+		// The message ID is 7 and the payload comes after three bytes of time of reception
+		if payload.len() < 8 {
+			return Err(Gdl90Error::Truncated{ msg_id: 7, needed: 11, got: payload.len() + 3 });
+		}
+		let raw:Vec<u8> = payload.clone();
 		let header:Vec<u8> = payload.drain(..8).collect();
 
 		// Decode the header based on Table 2-4, pg. 52 of "Manual for the Universal Access Transceiver"
-		let mut rdr = Cursor::new(header);
-		let header_msp:u32 = rdr.read_u32::<BigEndian>()?;
-		let header_lsp:u32 = rdr.read_u32::<BigEndian>()?;
+		let t = truncated(7, 11, &header);
+		let mut rdr = Cursor::new(&header);
+		let header_msp:u32 = rdr.read_u32::<BigEndian>().map_err(&t)?;
+		let header_lsp:u32 = rdr.read_u32::<BigEndian>().map_err(&t)?;
 
 		// Lat/Lon encoding is the same as described in 3.2.1.5.2.1
 		let ground_station_latitude_deg:f32 = {
@@ -35,10 +50,9 @@ impl Payload {
 			let angular_part:u32 = raw % 2097152;
 			match raw >> 21 {
 				0 => (angular_part as f32) * LAT_LON_LSB,
-				1 => if angular_part == 0 { 90.0 } else { return Err(Error::new(ErrorKind::Other, "Latitude: nonzero angular part in quadrant 1")); },
-				2 => return Err(Error::new(ErrorKind::Other, "Latitude in quadrant 2")),
+				1 => if angular_part == 0 { 90.0 } else { return Err(Gdl90Error::InvalidField{ field: "ground station latitude", value: raw }); },
 				3 => (angular_part as f32) * LAT_LON_LSB - 90.0,
-				_ => return Err(Error::new(ErrorKind::Other, "Invalid value for latitude quadrant")),
+				_ => return Err(Gdl90Error::InvalidField{ field: "ground station latitude quadrant", value: raw >> 21 }),
 			}
 		};
 		let ground_station_longitude_deg:f32 = {
@@ -47,7 +61,7 @@ impl Payload {
 			match raw >> 23 {
 				0 => (angular_part as f32) * LAT_LON_LSB,
 				1 => (angular_part as f32) * LAT_LON_LSB - 180.0,
-				_ => return Err(Error::new(ErrorKind::Other, "Invalid value for longitude quadrant")),
+				_ => return Err(Gdl90Error::InvalidField{ field: "ground station longitude quadrant", value: raw >> 23 }),
 			}
 		};
 		// TODO: consider checking to make sure the ground station is within a reasonable radius of the ownship
@@ -57,68 +71,188 @@ impl Payload {
 			application_data.push(frame); 
 		}
 
-		Ok(Payload{ ground_station_latitude_deg, ground_station_longitude_deg, application_data })
+		Ok(Payload{ ground_station_latitude_deg, ground_station_longitude_deg, application_data, raw })
+	}
+
+	pub fn ground_station_latitude_deg(&self) -> f32 {
+		self.ground_station_latitude_deg
+	}
+
+	pub fn ground_station_longitude_deg(&self) -> f32 {
+		self.ground_station_longitude_deg
+	}
+
+	pub fn application_data(&self) -> &[Frame] {
+		&self.application_data
+	}
+
+	pub fn raw(&self) -> &[u8] {
+		&self.raw
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		self.raw.clone()
+	}
+
+}
+
+// Time that a product applies to.  Which fields are present depends on the t_opt field of the APDU header
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ProductTime {
+	pub month: Option<u8>,
+	pub day: Option<u8>,
+	pub hours: u8,
+	pub minutes: u8,
+	pub seconds: Option<u8>,
+}
+
+// Present when the S flag is set, meaning that the product file was too big for one APDU and has been split up
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Segment {
+	pub product_file_id: u16,
+	// Number of APDUs that make up the product file
+	pub product_file_length: u16,
+	// Starts from 1
+	pub apdu_number: u16,
+}
+
+// APDU header from DO-358 section 2.2.3.  FIS-B doesn't define any optional fields for the A (application method),
+// G (geographic locator), and P (provider specific) flags, so they're reported but nothing else is read for them
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ApduHeader {
+	pub application_flag: bool,
+	pub geo_flag: bool,
+	pub provider_flag: bool,
+	pub product_id: u16,
+	pub time: ProductTime,
+	pub segment: Option<Segment>,
+}
+
+// Reads len bits, most significant first, starting at bit start.  The caller has to make sure that the data is long
+// enough
+fn bits(data:&[u8], start:usize, len:usize) -> u32 {
+	(start..start+len).fold(0, |acc, i| (acc << 1) | ((data[i / 8] >> (7 - i % 8)) & 0x01) as u32)
+}
+
+impl ApduHeader {
+
+	// Decodes the header at the start of an APDU and returns it with its length in bytes
+	pub fn from_slice(data:&[u8]) -> Result<(ApduHeader, usize), Gdl90Error> {
+		// The flags, product ID, S flag, and t_opt take 17 bits, which is enough to work out the rest of the length
+		if data.len() < 3 {
+			return Err(Gdl90Error::Truncated{ msg_id: 7, needed: 4, got: data.len() });
+		}
+		let sf:bool   = bits(data, 14, 1) == 1;
+		let t_opt:u32 = bits(data, 15, 2);
+		let time_len:usize = match t_opt {
+			0 => 11,
+			1 => 17,
+			2 => 20,
+			_ => 26,
+		};
+		let segment_len:usize = if sf { 28 } else { 0 };
+		let len:usize = (17 + time_len + segment_len).div_ceil(8);
+		if data.len() < len {
+			return Err(Gdl90Error::Truncated{ msg_id: 7, needed: len, got: data.len() });
+		}
+
+		let mut pos:usize = 17;
+		let mut field = |n:usize| { let x = bits(data, pos, n); pos += n; x };
+
+		let (month, day) = if t_opt & 0x02 != 0 { (Some(field(4)), Some(field(5))) } else { (None, None) };
+		let hours:u32   = field(5);
+		let minutes:u32 = field(6);
+		let seconds     = if t_opt & 0x01 != 0 { Some(field(6)) } else { None };
+
+		if hours   > 23 { return Err(Gdl90Error::InvalidField{ field: "APDU hours",   value: hours });   }
+		if minutes > 59 { return Err(Gdl90Error::InvalidField{ field: "APDU minutes", value: minutes }); }
+		if let Some(x) = seconds.filter(|x| *x > 59)                { return Err(Gdl90Error::InvalidField{ field: "APDU seconds", value: x }); }
+		if let Some(x) = month.filter(|x| !(1..=12).contains(x))    { return Err(Gdl90Error::InvalidField{ field: "APDU month",   value: x }); }
+		if let Some(x) = day.filter(|x| !(1..=31).contains(x))      { return Err(Gdl90Error::InvalidField{ field: "APDU day",     value: x }); }
+
+		let segment:Option<Segment> = if sf {
+			Some(Segment {
+				product_file_id:     field(10) as u16,
+				product_file_length: field(9) as u16,
+				apdu_number:         field(9) as u16,
+			})
+		} else {
+			None
+		};
+
+		let header = ApduHeader {
+			application_flag: bits(data, 0, 1) == 1,
+			geo_flag:         bits(data, 1, 1) == 1,
+			provider_flag:    bits(data, 2, 1) == 1,
+			product_id:       bits(data, 3, 11) as u16,
+			time: ProductTime {
+				month:   month.map(|x| x as u8),
+				day:     day.map(|x| x as u8),
+				hours:   hours as u8,
+				minutes: minutes as u8,
+				seconds: seconds.map(|x| x as u8),
+			},
+			segment,
+		};
+		Ok((header, len))
 	}
 
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Frame {
-	NexradPrecipitationImage{ hours:u32, minutes:u32 },
-	GenericText{ hours:u32, minutes:u32, text: text::Text },
-	Unknown{ id:u32, payload:Vec<u8>, hours:u32, minutes:u32 },
+	// Regional (product 63) or CONUS (product 64) precipitation
+	NexradPrecipitationImage{ header:ApduHeader, product:nexrad::NexradProduct, blocks:Vec<nexrad::Block> },
+	GenericText{ header:ApduHeader, text: text::Text },
+	// One piece of a product file that was split across several APDUs, which can't be decoded until all of the
+	// pieces have been received
+	Segmented{ header:ApduHeader, payload:Vec<u8> },
+	Unknown{ header:ApduHeader, payload:Vec<u8> },
 }
 
 impl Frame {
 
-	fn decode_apdu(apdu_header:Vec<u8>, apdu_payload:Vec<u8>) -> std::io::Result<Frame> {
-		let header:u32 = {
-			let mut rdr = Cursor::new(apdu_header);
-			rdr.read_u32::<BigEndian>()?
-		};
+	fn decode_apdu(apdu:&[u8]) -> Result<Frame, Gdl90Error> {
+		let (header, header_len) = ApduHeader::from_slice(apdu)?;
+		let apdu_payload:Vec<u8> = apdu[header_len..].to_vec();
 
-		// Not sure exactly what these flags mean, but they should all be false
-		// for the only two message types we currently know about.  It also seems
-		// to be true most almost all unknown messages
-		let af:bool = header & 0x80000000 == 0x80000000;
-		let gf:bool = header & 0x40000000 == 0x40000000;
-		let pf:bool = header & 0x20000000 == 0x20000000;
-		let sf:bool = header & 0x00020000 == 0x00020000;
-		if af|gf|pf|sf {
-			return Err(Error::new(ErrorKind::Other, "Flags in APDU header not as expected"));
+		if header.segment.is_some() {
+			Ok(Frame::Segmented{ header, payload: apdu_payload })
+		} else {
+			Ok(Frame::from_product(header, apdu_payload))
 		}
-		
-		let product_id:u32 = (header >> 18) % 2048;
-		let hours:u32      = (header >> 10) % 32;
-		let minutes:u32    = (header >>  4) % 64;
-		if hours   > 23 { return Err(Error::new(ErrorKind::Other, "Invalid hours value"));   }
-		if minutes > 59 { return Err(Error::new(ErrorKind::Other, "Invalid minutes value")); }
-
-		match product_id {
-			63 => {
-				Ok(Frame::NexradPrecipitationImage{ hours, minutes })
+	}
+
+	// Decodes a whole product file, which is either the payload of an unsegmented APDU or the reassembled payloads
+	// of a segmented one
+	pub(crate) fn from_product(header:ApduHeader, apdu_payload:Vec<u8>) -> Frame {
+		match header.product_id {
+			63 | 64 => {
+				let product = nexrad::NexradProduct::from_product_id(header.product_id).unwrap();
+				match nexrad::decode_blocks(&apdu_payload) {
+					Ok(blocks) => Frame::NexradPrecipitationImage{ header, product, blocks },
+					Err(_)     => Frame::Unknown{ header, payload: apdu_payload },
+				}
 			},
 			413 => {
 				let mut decoder = dlac::Decoder::new();
 				for b in apdu_payload { decoder.next(b); }
-				Ok(Frame::GenericText{ hours, minutes, text: text::Text::from_string(decoder.get_result()) })
+				Frame::GenericText{ header, text: text::Text::from_string(decoder.get_result()) }
 			},
-			id => Ok(Frame::Unknown{ id, payload: apdu_payload, hours, minutes }),
+			_ => Frame::Unknown{ header, payload: apdu_payload },
 			// Unkown frame possibilities: winds aloft, SIGMETs, AIRMETs, SUA
 		}
-
-		
 	}
 
-	pub fn new(payload:&mut Vec<u8>) -> std::io::Result<Frame> {
+	pub fn new(payload:&mut Vec<u8>) -> Result<Frame, Gdl90Error> {
 		// We need at least two bytes to get a frame header, which has the length and frame type
 		if payload.len() > 2 {
 			let header:u16 = {
 				// There might be a more elegant way to do this later, but right now I just want to make sure
 				// it works and make sure it takes two bytes off the payload vector
 				let bytes:Vec<u8> = payload.drain(..2).collect();
-				let mut rdr = Cursor::new(bytes);
-				rdr.read_u16::<BigEndian>()?
+				let mut rdr = Cursor::new(&bytes);
+				rdr.read_u16::<BigEndian>().map_err(truncated(7, 2, &bytes))?
 			};
 
 			// The 9 most significant bits are the length.  The next 3 bytes are reserved and the last 4 are the frame type
@@ -127,28 +261,27 @@ impl Frame {
 
 			// After the frame header, we need at least four more bytes for the APDU header
 			// ADPU is "Application Protocol Data Unit"
-			if length < 4 { return Err(Error::new(ErrorKind::Other, "No APDU header")); }
+			if length < 4 { return Err(Gdl90Error::InvalidField{ field: "frame length", value: length as u32 }); }
 
 			// Frame type 0 is "FIS-B APDU", all other values are reserved or experimental
-			if frame_type != 0 { return Err(Error::new(ErrorKind::Other, "Frame type other than FIS-B APDU")); }
+			if frame_type != 0 { return Err(Gdl90Error::InvalidField{ field: "frame type", value: frame_type as u32 }); }
 
 			if length == 0 {
 				// These zero-length messages are mostly just used for padding
-				Err(Error::new(ErrorKind::Other, "Zero-length message"))
+				Err(Gdl90Error::InvalidField{ field: "frame length", value: 0 })
 			} else if payload.len() < length as usize {
 				// The length given in the frame header must be wrong or we got an incomplete message
-				Err(Error::new(ErrorKind::Other, "Fewer remaining bytes that the message length"))
+				Err(Gdl90Error::Truncated{ msg_id: 7, needed: length as usize, got: payload.len() })
 			} else {
 
 				// We passed all the tests at the frame level, so go down to the APDU level and see if we get a valid message
-				let apdu_header:Vec<u8>  = payload.drain(..4).collect();
-				let apdu_payload:Vec<u8> = payload.drain(..((length-4) as usize)).collect();
-				
-				Frame::decode_apdu(apdu_header, apdu_payload)
+				let apdu:Vec<u8> = payload.drain(..(length as usize)).collect();
+
+				Frame::decode_apdu(&apdu)
 
 			}
 		} else {
-			Err(Error::new(ErrorKind::Other, "Not enough bytes for a message"))
+			Err(Gdl90Error::Truncated{ msg_id: 7, needed: 3, got: payload.len() })
 		}
 	}
 
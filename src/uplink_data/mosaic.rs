@@ -0,0 +1,176 @@
+extern crate serde;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use serde::{Serialize, Deserialize};
+
+use super::Frame;
+use super::nexrad::{Block, BINS_PER_ROW, ROWS};
+
+// The mosaic grid is the size of the smallest NEXRAD bin: 1 arcminute of latitude by 1.5 arcminutes of longitude
+const CELLS_PER_DEG_LAT:f32 = 60.0;
+const CELLS_PER_DEG_LON:f32 = 40.0;
+
+// Cells of a raster that no block covers
+pub const NO_DATA:u8 = 0xFF;
+
+// Blocks are replaced by newer blocks with the same number, hemisphere, and scale, so regional and CONUS blocks over
+// the same area are kept separately
+type Key = (u32, bool, u8);
+
+struct Entry {
+	block: Block,
+	time: SystemTime,
+}
+
+// Precipitation intensities on a lat/lon grid
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Raster {
+	pub north_lat_deg: f32,
+	pub west_lon_deg: f32,
+	pub south_lat_deg: f32,
+	pub east_lon_deg: f32,
+	pub width: usize,
+	pub height: usize,
+	// Intensities from 0 to 7 row by row from the north-west corner, or NO_DATA
+	pub cells: Vec<u8>,
+}
+
+// Keeps the most recent NEXRAD blocks so that they can be drawn together.  Where blocks overlap, finer blocks are
+// drawn over coarser ones, and newer blocks over older ones of the same scale.  Rasters don't wrap around the
+// antimeridian
+pub struct NexradMosaic {
+	blocks: HashMap<Key, Entry>,
+	max_age: Duration,
+}
+
+impl NexradMosaic {
+
+	// Blocks are dropped by expire once they're older than max_age
+	pub fn new(max_age:Duration) -> NexradMosaic {
+		NexradMosaic{ blocks: HashMap::new(), max_age }
+	}
+
+	// Adds the blocks in a frame received now, returning how many there were.  Frames other than NEXRAD images are
+	// ignored
+	pub fn insert(&mut self, frame:&Frame) -> usize {
+		self.insert_at(frame, SystemTime::now())
+	}
+
+	pub fn insert_at(&mut self, frame:&Frame, now:SystemTime) -> usize {
+		match frame {
+			Frame::NexradPrecipitationImage{ blocks, .. } => {
+				for block in blocks {
+					self.insert_block(block.clone(), now);
+				}
+				blocks.len()
+			},
+			_ => 0,
+		}
+	}
+
+	pub fn insert_block(&mut self, block:Block, now:SystemTime) {
+		self.blocks.insert((block.block_number, block.southern_hemisphere, block.scale), Entry{ block, time: now });
+	}
+
+	// Drops blocks older than the maximum age and returns how many were dropped
+	pub fn expire(&mut self, now:SystemTime) -> usize {
+		let max_age:Duration = self.max_age;
+		let before:usize = self.blocks.len();
+		self.blocks.retain(|_, entry| now.duration_since(entry.time).map(|age| age <= max_age).unwrap_or(true));
+		before - self.blocks.len()
+	}
+
+	pub fn len(&self) -> usize {
+		self.blocks.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.blocks.is_empty()
+	}
+
+	// A raster just big enough for every block, or None if there aren't any
+	pub fn raster(&self) -> Option<Raster> {
+		let extents = self.blocks.values().map(|entry| {
+			let (north, west, bin_height, bin_width) = entry.block.grid_extent();
+			(north, west, north - bin_height*(ROWS as i32), west + bin_width*(BINS_PER_ROW as i32))
+		});
+		let (north, west, south, east) = extents.reduce(|a, b| (a.0.max(b.0), a.1.min(b.1), a.2.min(b.2), a.3.max(b.3)))?;
+		Some(self.render(north, west, south, east))
+	}
+
+	// A raster covering the given area, expanded slightly to fit the grid
+	pub fn raster_within(&self, north_lat_deg:f32, west_lon_deg:f32, south_lat_deg:f32, east_lon_deg:f32) -> Raster {
+		// Allow a little rounding error so that bounds on the grid stay there
+		let up   = |x:f32| (x - 1e-3).ceil() as i32;
+		let down = |x:f32| (x + 1e-3).floor() as i32;
+		self.render(up(north_lat_deg * CELLS_PER_DEG_LAT), down(west_lon_deg * CELLS_PER_DEG_LON),
+			down(south_lat_deg * CELLS_PER_DEG_LAT), up(east_lon_deg * CELLS_PER_DEG_LON))
+	}
+
+	// Bounds are in grid cells, like Block::grid_extent
+	fn render(&self, north:i32, west:i32, south:i32, east:i32) -> Raster {
+		let height:usize = (north - south).max(0) as usize;
+		let width:usize  = (east - west).max(0) as usize;
+		let mut cells:Vec<u8> = vec![NO_DATA; width * height];
+
+		let mut entries:Vec<&Entry> = self.blocks.values().collect();
+		entries.sort_by(|a, b| b.block.scale.cmp(&a.block.scale).then(a.time.cmp(&b.time)));
+
+		for entry in entries {
+			let (block_north, block_west, bin_height, bin_width) = entry.block.grid_extent();
+			for (i, intensity) in entry.block.bins.iter().enumerate() {
+				let top:i32  = north - (block_north - bin_height*(i / BINS_PER_ROW) as i32);
+				let left:i32 = block_west + bin_width*(i % BINS_PER_ROW) as i32 - west;
+				for y in top.max(0)..(top + bin_height).min(height as i32) {
+					for x in left.max(0)..(left + bin_width).min(width as i32) {
+						cells[y as usize * width + x as usize] = *intensity;
+					}
+				}
+			}
+		}
+
+		Raster {
+			north_lat_deg: north as f32 / CELLS_PER_DEG_LAT,
+			west_lon_deg:  west  as f32 / CELLS_PER_DEG_LON,
+			south_lat_deg: south as f32 / CELLS_PER_DEG_LAT,
+			east_lon_deg:  east  as f32 / CELLS_PER_DEG_LON,
+			width, height, cells,
+		}
+	}
+
+}
+
+impl Raster {
+
+	// Intensity at a point, or None if it's outside the raster
+	pub fn get(&self, lat_deg:f32, lon_deg:f32) -> Option<u8> {
+		let y:f32 = (self.north_lat_deg - lat_deg) * CELLS_PER_DEG_LAT;
+		let x:f32 = (lon_deg - self.west_lon_deg) * CELLS_PER_DEG_LON;
+		if y < 0.0 || x < 0.0 || y >= self.height as f32 || x >= self.width as f32 {
+			return None;
+		}
+		Some(self.cells[y as usize * self.width + x as usize])
+	}
+
+	// Writes a binary PGM image with intensities spread from black to white.  Cells without data are black, like
+	// cells without precipitation
+	pub fn write_pgm<W:Write>(&self, w:&mut W) -> io::Result<()> {
+		write!(w, "P5\n{} {}\n255\n", self.width, self.height)?;
+		let pixels:Vec<u8> = self.cells.iter()
+			.map(|x| if *x == NO_DATA { 0 } else { ((*x as u16 * 255) / 7) as u8 })
+			.collect();
+		w.write_all(&pixels)
+	}
+
+	pub fn save_pgm<P:AsRef<Path>>(&self, path:P) -> io::Result<()> {
+		let mut w = BufWriter::new(File::create(path)?);
+		self.write_pgm(&mut w)?;
+		w.flush()
+	}
+
+}
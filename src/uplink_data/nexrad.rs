@@ -0,0 +1,158 @@
+extern crate serde;
+
+use serde::{Serialize, Deserialize};
+
+use crate::Gdl90Error;
+
+// Each block is a grid of 32 bins west to east by 4 bins north to south
+pub const BINS_PER_ROW:usize = 32;
+pub const ROWS:usize         = 4;
+pub const BINS:usize         = BINS_PER_ROW * ROWS;
+
+// Block numbers count west to east around each 4 arcminute ring of latitude, starting at the equator and the prime
+// meridian.  Above 60 degrees only even block numbers are used, and blocks are twice as wide
+const BLOCKS_PER_RING:u32   = 450;
+const WIDE_BLOCK_START:u32  = 405000;
+const BLOCK_HEIGHT_DEG:f32  = 4.0 / 60.0;
+const BLOCK_WIDTH_DEG:f32   = 48.0 / 60.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum NexradProduct {
+	Regional,
+	Conus,
+}
+
+impl NexradProduct {
+
+	pub fn from_product_id(product_id:u16) -> Option<NexradProduct> {
+		match product_id {
+			63 => Some(NexradProduct::Regional),
+			64 => Some(NexradProduct::Conus),
+			_  => None,
+		}
+	}
+
+}
+
+// One block of precipitation intensities from 0 (none) to 7 (most intense), along with the area it covers.  Scaled
+// blocks cover 5 or 9 times the height and width of a normal block, extending away from the equator
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Block {
+	pub block_number: u32,
+	// 1, 5, or 9
+	pub scale: u8,
+	pub southern_hemisphere: bool,
+	pub north_lat_deg: f32,
+	pub west_lon_deg: f32,
+	pub height_deg: f32,
+	pub width_deg: f32,
+	// BINS intensities, row by row from the north-west corner
+	pub bins: Vec<u8>,
+}
+
+impl Block {
+
+	fn new(block_number:u32, scale:u8, southern_hemisphere:bool, bins:Vec<u8>) -> Block {
+		let ring:u32 = block_number / BLOCKS_PER_RING;
+		let height_deg:f32 = BLOCK_HEIGHT_DEG * scale as f32;
+		let width_deg:f32  = if block_number >= WIDE_BLOCK_START { 2.0 * BLOCK_WIDTH_DEG } else { BLOCK_WIDTH_DEG } * scale as f32;
+
+		// The edge nearest the equator
+		let equator_edge_deg:f32 = ring as f32 * BLOCK_HEIGHT_DEG;
+		let north_lat_deg:f32 = if southern_hemisphere { -equator_edge_deg } else { equator_edge_deg + height_deg };
+		let east_of_meridian_deg:f32 = (block_number % BLOCKS_PER_RING) as f32 * BLOCK_WIDTH_DEG;
+		let west_lon_deg:f32 = if east_of_meridian_deg >= 180.0 { east_of_meridian_deg - 360.0 } else { east_of_meridian_deg };
+
+		Block{ block_number, scale, southern_hemisphere, north_lat_deg, west_lon_deg, height_deg, width_deg, bins }
+	}
+
+	pub fn south_lat_deg(&self) -> f32 {
+		self.north_lat_deg - self.height_deg
+	}
+
+	pub fn east_lon_deg(&self) -> f32 {
+		self.west_lon_deg + self.width_deg
+	}
+
+	// Intensity of the bin in the given row (from the north) and column (from the west)
+	pub fn bin(&self, row:usize, col:usize) -> u8 {
+		self.bins[row * BINS_PER_ROW + col]
+	}
+
+	// The north-west corner of the block and the size of its bins on a grid of the smallest bins, which are 1
+	// arcminute high and 1.5 arcminutes wide.  Rows count north from the equator and columns count east from the
+	// prime meridian, between -7200 and 7200
+	pub(crate) fn grid_extent(&self) -> (i32, i32, i32, i32) {
+		let ring:i32  = (self.block_number / BLOCKS_PER_RING) as i32;
+		let scale:i32 = self.scale as i32;
+		let north:i32 = if self.southern_hemisphere { -(ROWS as i32) * ring } else { (ROWS as i32) * (ring + scale) };
+		let west:i32  = ((self.block_number % BLOCKS_PER_RING) as usize * BINS_PER_ROW) as i32;
+		let west:i32  = if west >= 7200 { west - 14400 } else { west };
+		let bin_width:i32 = if self.block_number >= WIDE_BLOCK_START { 2 * scale } else { scale };
+		(north, west, scale, bin_width)
+	}
+
+}
+
+// The other blocks in the same ring, offset blocks to the east of this one
+fn block_in_ring(block_number:u32, offset:u32) -> u32 {
+	let ring_start:u32 = block_number - block_number % BLOCKS_PER_RING;
+	if block_number >= WIDE_BLOCK_START {
+		ring_start + 2 * ((block_number % BLOCKS_PER_RING / 2 + offset) % (BLOCKS_PER_RING / 2))
+	} else {
+		ring_start + (block_number % BLOCKS_PER_RING + offset) % BLOCKS_PER_RING
+	}
+}
+
+// Decodes the payload of a NEXRAD product (63 or 64).  A run-length encoded payload has one block, and an empty block
+// payload has a bitmap of blocks in the same ring that have no precipitation
+pub fn decode_blocks(data:&[u8]) -> Result<Vec<Block>, Gdl90Error> {
+	// Block reference indicator: RLE flag, hemisphere, scale factor, and a 20-bit block number
+	if data.len() < 4 {
+		return Err(Gdl90Error::Truncated{ msg_id: 7, needed: 4, got: data.len() });
+	}
+	let rle:bool                 = data[0] & 0x80 != 0;
+	let southern_hemisphere:bool = data[0] & 0x40 != 0;
+	let scale:u8 = match (data[0] >> 4) & 0x03 {
+		0 => 1,
+		1 => 5,
+		2 => 9,
+		x => return Err(Gdl90Error::InvalidField{ field: "NEXRAD scale factor", value: x as u32 }),
+	};
+	let mut block_number:u32 = (((data[0] & 0x0F) as u32) << 16) | ((data[1] as u32) << 8) | (data[2] as u32);
+	if block_number >= WIDE_BLOCK_START {
+		block_number &= !1;
+	}
+
+	if rle {
+		// Each byte is a run length minus one in the upper five bits and an intensity in the lower three
+		let mut bins:Vec<u8> = Vec::with_capacity(BINS);
+		for b in &data[3..] {
+			let run:usize = (b >> 3) as usize + 1;
+			bins.resize(bins.len() + run, b & 0x07);
+		}
+		if bins.len() != BINS {
+			return Err(Gdl90Error::InvalidField{ field: "NEXRAD bin count", value: bins.len() as u32 });
+		}
+		return Ok(vec![Block::new(block_number, scale, southern_hemisphere, bins)]);
+	}
+
+	// The low nibble of the first byte is the length of the bitmap in bytes, including that one.  The upper nibble
+	// of that byte covers the four blocks after the reference block, which is always empty, and each following byte
+	// covers the next eight
+	let len:usize = ((data[3] & 0x0F) as usize).max(1);
+	if data.len() < 3 + len {
+		return Err(Gdl90Error::Truncated{ msg_id: 7, needed: 3 + len, got: data.len() });
+	}
+	let mut blocks:Vec<Block> = vec![Block::new(block_number, scale, southern_hemisphere, vec![0; BINS])];
+	for (i, b) in data[3..3+len].iter().enumerate() {
+		let first_bit:u32 = if i == 0 { 4 } else { 0 };
+		for bit in first_bit..8 {
+			if b & (1 << bit) != 0 {
+				let offset:u32 = 8 * i as u32 + bit - 3;
+				blocks.push(Block::new(block_in_ring(block_number, offset), scale, southern_hemisphere, vec![0; BINS]));
+			}
+		}
+	}
+	Ok(blocks)
+}
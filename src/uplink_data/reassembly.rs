@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use crate::Gdl90Error;
+use super::{ApduHeader, Frame, Payload};
+
+// Segments are kept apart by the ground station that sent them, the product, and the product file ID.  The ground
+// station position is compared exactly, since it's decoded the same way from every uplink the station sends
+type Key = (u32, u32, u16, u16);
+
+struct Partial {
+	header: ApduHeader,
+	segments: Vec<Option<Vec<u8>>>,
+	received: usize,
+	first_time: SystemTime,
+}
+
+// A product file put back together from all of its segments
+#[derive(Debug)]
+pub struct Product {
+	pub ground_station_latitude_deg: f32,
+	pub ground_station_longitude_deg: f32,
+	// Decoded from the concatenated segment payloads.  The header is the one from the first segment received
+	pub frame: Frame,
+}
+
+// Collects segments of product files that were split across several APDUs, possibly in different uplinks, and
+// returns each product once all of its segments are in
+pub struct Reassembler {
+	partial: HashMap<Key, Partial>,
+	timeout: Duration,
+}
+
+impl Reassembler {
+
+	// Incomplete products are dropped once this long has passed since their first segment was received
+	pub fn new(timeout:Duration) -> Reassembler {
+		Reassembler{ partial: HashMap::new(), timeout }
+	}
+
+	// Adds every segment in an uplink received now and returns the products that it completed
+	pub fn add(&mut self, payload:&Payload) -> Vec<Product> {
+		self.add_at(payload, SystemTime::now())
+	}
+
+	// Segments with invalid numbering are skipped
+	pub fn add_at(&mut self, payload:&Payload, now:SystemTime) -> Vec<Product> {
+		self.expire(now);
+
+		payload.application_data.iter()
+			.filter_map(|frame| match frame {
+				Frame::Segmented{ header, payload: data } => self.add_segment(payload.ground_station_latitude_deg,
+					payload.ground_station_longitude_deg, header, data, now).ok().flatten(),
+				_ => None,
+			})
+			.collect()
+	}
+
+	// Adds one segment, returning the product if this was the last segment needed
+	pub fn add_segment(&mut self, gs_lat_deg:f32, gs_lon_deg:f32, header:&ApduHeader, data:&[u8], now:SystemTime)
+		-> Result<Option<Product>, Gdl90Error> {
+
+		let segment = header.segment.ok_or(Gdl90Error::Unrecognized("APDU isn't segmented"))?;
+		let count:usize  = segment.product_file_length as usize;
+		let number:usize = segment.apdu_number as usize;
+		if number == 0 || number > count {
+			return Err(Gdl90Error::InvalidField{ field: "APDU number", value: number as u32 });
+		}
+
+		let key:Key = (gs_lat_deg.to_bits(), gs_lon_deg.to_bits(), header.product_id, segment.product_file_id);
+		let partial = self.partial.entry(key).or_insert_with(|| Partial {
+			header: *header, segments: vec![None; count], received: 0, first_time: now,
+		});
+
+		// A different length means the file ID has been reused for a new product, so start over
+		if partial.segments.len() != count {
+			*partial = Partial{ header: *header, segments: vec![None; count], received: 0, first_time: now };
+		}
+
+		if partial.segments[number - 1].is_none() {
+			partial.received += 1;
+		}
+		partial.segments[number - 1] = Some(data.to_vec());
+
+		if partial.received < count {
+			return Ok(None);
+		}
+
+		let partial = self.partial.remove(&key).unwrap();
+		let data:Vec<u8> = partial.segments.into_iter().flatten().flatten().collect();
+		Ok(Some(Product {
+			ground_station_latitude_deg:  gs_lat_deg,
+			ground_station_longitude_deg: gs_lon_deg,
+			frame: Frame::from_product(partial.header, data),
+		}))
+	}
+
+	// Drops incomplete products that have timed out and returns how many were dropped
+	pub fn expire(&mut self, now:SystemTime) -> usize {
+		let timeout:Duration = self.timeout;
+		let before:usize = self.partial.len();
+		self.partial.retain(|_, partial| now.duration_since(partial.first_time).map(|age| age <= timeout).unwrap_or(true));
+		before - self.partial.len()
+	}
+
+	// Number of products with some but not all of their segments
+	pub fn len(&self) -> usize {
+		self.partial.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.partial.is_empty()
+	}
+
+}
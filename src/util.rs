@@ -1,11 +1,35 @@
+use crate::geodesy::{self, EarthModel};
+
+// These work in f32 like the rest of the crate, but do the geodesy in f64 on WGS-84
 
 pub fn lat_lon_dist_nm(phi1_deg:f32, lam1_deg:f32, phi2_deg:f32, lam2_deg:f32) -> f32 {
-	let phi1:f32 = phi1_deg * crate::RAD_PER_DEG;
-	let phi2:f32 = phi2_deg * crate::RAD_PER_DEG;
-	let dphi:f32 = phi2 - phi1;
-	let dlam:f32 = (lam2_deg - lam1_deg) * crate::RAD_PER_DEG;
-	let a:f32 = (0.5 * dphi).sin().powi(2) + phi1.cos()*phi2.cos()*(0.5*dlam).sin().powi(2);
-	let c:f32 = 2.0 * a.sqrt().atan2((1.0-a).sqrt());
-
-	(crate::R * c) / crate::METERS_PER_NM
+	let g = geodesy::inverse(EarthModel::default(), phi1_deg as f64, lam1_deg as f64, phi2_deg as f64, lam2_deg as f64);
+	(g.distance_m / crate::METERS_PER_NM as f64) as f32
+}
+
+// Initial bearing along the shortest path from the first point to the second, from 0 to 360 degrees true
+pub fn initial_bearing_deg(phi1_deg:f32, lam1_deg:f32, phi2_deg:f32, lam2_deg:f32) -> f32 {
+	let g = geodesy::inverse(EarthModel::default(), phi1_deg as f64, lam1_deg as f64, phi2_deg as f64, lam2_deg as f64);
+	g.initial_bearing_deg as f32
+}
+
+// The point at the given range and initial bearing from a starting point; the inverse of lat_lon_dist_nm and
+// initial_bearing_deg
+pub fn destination_lat_lon(phi1_deg:f32, lam1_deg:f32, range_nm:f32, bearing_deg:f32) -> (f32, f32) {
+	let distance_m:f64 = range_nm as f64 * crate::METERS_PER_NM as f64;
+	let (phi2_deg, lam2_deg) = geodesy::direct(EarthModel::default(), phi1_deg as f64, lam1_deg as f64, bearing_deg as f64, distance_m);
+	(phi2_deg as f32, lam2_deg as f32)
+}
+
+// Bearing relative to the nose, from 0 to 360 degrees clockwise
+pub fn relative_bearing_deg(bearing_deg:f32, track_deg:f32) -> f32 {
+	(bearing_deg - track_deg).rem_euclid(360.0)
+}
+
+// The nearest clock position to a relative bearing, from 1 to 12 o'clock
+pub fn clock_position(relative_bearing_deg:f32) -> u8 {
+	match ((relative_bearing_deg.rem_euclid(360.0) / 30.0).round() as u8) % 12 {
+		0 => 12,
+		x => x,
+	}
 }